@@ -0,0 +1,515 @@
+//! Goal oriented action planning.
+//!
+//! The objective of GOAP is for an `Agent` to find a way from the current `State` -> goal `State` through `Action`s.
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+use std::hash::{BuildHasher, RandomState};
+use std::marker::PhantomData;
+
+use rand::RngCore;
+
+/// The number of states the planner will expand for a goal before deciding it can't be reached.
+pub const PATIENCE: usize = 2000;
+
+/// How many times over the planner trusts `State::estimate`. Anything above 1 searches greedily towards the goal, so
+/// plans can cost up to this many times the cheapest but are found far quicker.
+pub const GREED: u64 = 2;
+
+/// State MUST be all-encompassing
+/// e.g. For an agent to pick something up; the information about the item, where it is, and the agent's inventory must all be included in the state.
+/// So we need some process for constructing and deconstructing the state for each agent.
+/// - States can then be augmented with agent perception.
+pub trait State: std::fmt::Debug + Clone + PartialEq + Eq + std::hash::Hash {
+    fn compare(&self, other_state: &Self) -> bool {
+        self == other_state
+    }
+
+    /// A lower bound on the cost of getting from this state to one that compares as `goal_state`, to point the search
+    /// the right way. Overestimating can cost the plan its cheapness, and the default of nothing is always safe.
+    fn estimate(&self, _goal_state: &Self) -> u64 {
+        0
+    }
+}
+
+/// Every `Action` available to an agent, wrapped up in one type so the planner can generate them on the fly.
+//
+// WARN: I've tried a million times to implement `Action` as a trait object but cannot manage it hence `ActionEnum`. A mixture of the pathfinding
+// crate (because of Node requiring Eq which is not object safe) and other things have forced this into an Enum.
+pub trait ActionEnum<S: State>: Action<S> {
+    fn generate_available_actions(current_state: &S) -> Vec<Self>;
+}
+
+/// Actions describe changes to the input State and can be generated on the fly. For example, a MoveAction moves the Agent toward a certain item.
+pub trait Action<S: State>: std::fmt::Debug + Clone + PartialEq + Eq + std::hash::Hash {
+    fn act(&self, current_state: S) -> S;
+
+    /// Number of ticks the action takes to carry out from `current_state`.
+    fn duration(&self, _current_state: &S) -> u64 {
+        1
+    }
+
+    /// What taking the action from `current_state` costs a plan. By default that's the time it takes, so the planner
+    /// finds the quickest plan.
+    fn cost(&self, current_state: &S) -> u64 {
+        self.duration(current_state)
+    }
+
+    fn prerequisite(&self, _current_state: &S) -> bool;
+}
+
+/// Progress report from carrying out an action (or a whole plan) for a tick.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Running,
+    Succeeded,
+    Failed,
+}
+
+/// Actions that can be carried out over time in the world, as opposed to `Action::act` which applies the whole
+/// effect at once for the planner.
+pub trait Executable<S: State>: Action<S> {
+    /// Advance the action by a single tick, `elapsed` being the number of ticks already spent on it and `rng` deciding
+    /// anything left to chance. By default the action waits out its duration and then plays out its `outcome`, failing
+    /// if its prerequisite stops holding.
+    fn execute(&self, current_state: &mut S, elapsed: u64, rng: &mut dyn RngCore) -> Status {
+        if !self.prerequisite(current_state) {
+            return Status::Failed;
+        }
+        if elapsed + 1 < self.duration(current_state) {
+            return Status::Running;
+        }
+        self.outcome(current_state, rng)
+    }
+
+    /// How the action really turns out once it's done, which can fail or differ from `act`. The planner only ever sees
+    /// `act`, so that should give the expected outcome and `cost` should allow for the odd failure. By default the
+    /// action always goes to plan.
+    fn outcome(&self, current_state: &mut S, _rng: &mut dyn RngCore) -> Status {
+        *current_state = self.act(current_state.clone());
+        Status::Succeeded
+    }
+
+    /// Called when the action is abandoned part way through so it can tidy up after itself.
+    fn cancel(&self, _current_state: &mut S) {}
+}
+
+/// Something an agent wants. The agent plans for whichever goal has the highest priority and can be reached, and a
+/// goal with a priority of zero or less is one the agent is content with for now.
+pub trait Goal<S: State> {
+    fn priority(&self, current_state: &S) -> i64;
+    fn goal_state(&self, current_state: S) -> S;
+}
+
+/// Find the cheapest sequence of actions from `current_state` to the goal state of the highest priority goal that
+/// can be reached, moving on to the next most pressing goal when one can't.
+pub fn plan<S: State, SA: ActionEnum<S>>(
+    current_state: S,
+    goals: &[Box<dyn Goal<S>>],
+) -> Option<Vec<SA>> {
+    by_priority(&current_state, goals)
+        .into_iter()
+        .find_map(|i| plan_for(&current_state, goals[i].as_ref()))
+}
+
+/// Indices of the goals worth planning for in `current_state`, most pressing first.
+pub fn by_priority<S: State>(current_state: &S, goals: &[Box<dyn Goal<S>>]) -> Vec<usize> {
+    let mut wanted: Vec<_> = goals
+        .iter()
+        .map(|g| g.priority(current_state))
+        .enumerate()
+        .filter(|(_, priority)| *priority > 0)
+        .collect();
+    wanted.sort_by_key(|(_, priority)| Reverse(*priority));
+    wanted.into_iter().map(|(i, _)| i).collect()
+}
+
+/// Find the cheapest sequence of actions from `current_state` to the goal state of `goal`, giving up once PATIENCE
+/// runs out.
+pub fn plan_for<S: State, SA: ActionEnum<S>>(
+    current_state: &S,
+    goal: &dyn Goal<S>,
+) -> Option<Vec<SA>> {
+    let mut unlimited = usize::MAX;
+    match Planning::new(current_state, goal).search(&mut unlimited) {
+        Search::Found(plan) => Some(plan),
+        Search::Pending | Search::Unreachable => None,
+    }
+}
+
+/// How a search for a plan stands.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Search<SA> {
+    /// Out of budget for now, with more searching to do.
+    Pending,
+    Found(Vec<SA>),
+    /// There's no plan, or none to be found within PATIENCE.
+    Unreachable,
+}
+
+// A state the search has come across, and the cheapest way it's found there so far.
+struct Node<S, SA> {
+    state: S,
+    cost: u64,
+    // The node before, and the action taken from it to get here. Only the start has neither.
+    step: Option<(usize, SA)>,
+}
+
+/// A search for a plan towards one goal that can be carried out a little at a time, so that planning can be spread
+/// over several ticks rather than holding everything else up.
+pub struct Planning<S: State, SA: ActionEnum<S>> {
+    goal_state: S,
+    nodes: Vec<Node<S, SA>>,
+    // Where each state is in `nodes`, by its hash. The search is over states alone, so the same state reached by
+    // different actions (or the same actions in a different order) is only ever expanded once. States can be big, so
+    // each is only hashed the once rather than every time the map grows.
+    seen: HashMap<u64, Vec<usize>>,
+    hasher: RandomState,
+    // Nodes waiting to be expanded, cheapest estimated total first, and among those the furthest along.
+    open: BinaryHeap<Reverse<(u64, Reverse<u64>, usize)>>,
+    expanded: usize,
+}
+
+impl<S: State, SA: ActionEnum<S>> Planning<S, SA> {
+    pub fn new(current_state: &S, goal: &dyn Goal<S>) -> Self {
+        let goal_state = goal.goal_state(current_state.clone());
+        let mut planning = Self {
+            goal_state,
+            nodes: vec![],
+            seen: HashMap::new(),
+            hasher: RandomState::new(),
+            open: BinaryHeap::new(),
+            expanded: 0,
+        };
+        planning.reach(current_state.clone(), 0, None);
+        planning
+    }
+
+    /// Carry the search on, expanding states until it comes to an end or `budget` runs out. Each state expanded takes
+    /// one off `budget`.
+    pub fn search(&mut self, budget: &mut usize) -> Search<SA> {
+        while let Some(&Reverse((_, Reverse(cost), i))) = self.open.peek() {
+            // Left behind when a cheaper way to the same state turned up.
+            if cost > self.nodes[i].cost {
+                self.open.pop();
+                continue;
+            }
+            if self.nodes[i].state.compare(&self.goal_state) {
+                return Search::Found(self.plan_to(i));
+            }
+            if self.expanded >= PATIENCE {
+                break;
+            }
+            if *budget == 0 {
+                return Search::Pending;
+            }
+            self.open.pop();
+            *budget -= 1;
+            self.expanded += 1;
+
+            let state = self.nodes[i].state.clone();
+            for action in SA::generate_available_actions(&state) {
+                let next = action.act(state.clone());
+                self.reach(next, cost + action.cost(&state), Some((i, action)));
+            }
+        }
+        Search::Unreachable
+    }
+
+    // Note down a way to `state` costing `cost`, unless there's already one at least as cheap.
+    fn reach(&mut self, state: S, cost: u64, step: Option<(usize, SA)>) {
+        let hash = self.hasher.hash_one(&state);
+        let same = self
+            .seen
+            .get(&hash)
+            .and_then(|nodes| nodes.iter().find(|&&i| self.nodes[i].state == state));
+        let i = match same {
+            Some(&i) if self.nodes[i].cost <= cost => return,
+            Some(&i) => {
+                self.nodes[i].cost = cost;
+                self.nodes[i].step = step;
+                i
+            }
+            None => {
+                let i = self.nodes.len();
+                self.seen.entry(hash).or_default().push(i);
+                self.nodes.push(Node { state, cost, step });
+                i
+            }
+        };
+        let estimate = cost + self.nodes[i].state.estimate(&self.goal_state) * GREED;
+        self.open.push(Reverse((estimate, Reverse(cost), i)));
+    }
+
+    // The actions taken to get from the start to node `i`.
+    fn plan_to(&self, mut i: usize) -> Vec<SA> {
+        let mut plan = vec![];
+        while let Some((previous, action)) = &self.nodes[i].step {
+            plan.push(action.clone());
+            i = *previous;
+        }
+        plan.reverse();
+        plan
+    }
+}
+
+/// Drives a plan one action at a time, a tick at a time.
+pub struct Executor<S: State, SA: Executable<S>> {
+    remaining: VecDeque<SA>,
+    current: Option<SA>,
+    elapsed: u64,
+    _state: PhantomData<S>,
+}
+
+impl<S: State, SA: Executable<S>> Executor<S, SA> {
+    pub fn new(plan: Vec<SA>) -> Self {
+        Self {
+            remaining: plan.into(),
+            current: None,
+            elapsed: 0,
+            _state: PhantomData,
+        }
+    }
+
+    /// `Running` while there is still work to do, `Succeeded` once every action has succeeded and `Failed` as soon as
+    /// any action fails, after which the rest of the plan is dropped.
+    pub fn tick(&mut self, current_state: &mut S, rng: &mut dyn RngCore) -> Status {
+        if self.current.is_none() {
+            self.current = self.remaining.pop_front();
+            self.elapsed = 0;
+        }
+
+        let Some(action) = &self.current else {
+            return Status::Succeeded;
+        };
+
+        match action.execute(current_state, self.elapsed, rng) {
+            Status::Running => {
+                self.elapsed += 1;
+                Status::Running
+            }
+            Status::Succeeded => {
+                self.current = None;
+                if self.remaining.is_empty() {
+                    Status::Succeeded
+                } else {
+                    Status::Running
+                }
+            }
+            Status::Failed => {
+                self.current = None;
+                self.remaining.clear();
+                Status::Failed
+            }
+        }
+    }
+
+    /// The action being carried out and the number of ticks spent on it so far.
+    pub fn progress(&self) -> Option<(&SA, u64)> {
+        self.current.as_ref().map(|action| (action, self.elapsed))
+    }
+
+    /// Abandon the plan, letting the action in progress tidy up.
+    pub fn cancel(&mut self, current_state: &mut S) {
+        if let Some(action) = self.current.take() {
+            action.cancel(current_state);
+        }
+        self.remaining.clear();
+    }
+}
+
+/// Each action of a plan, set out one after another for debugging. It's left to the caller to decide where (if
+/// anywhere) that goes.
+pub fn describe_plan<S: State, SA: Action<S>>(plan: &[SA]) -> String {
+    let mut description = String::new();
+    for agent_action in plan {
+        description.push_str("---------------------------\n");
+        description.push_str(&format!("Action: {:#?}\n", agent_action));
+    }
+    description.push_str("---------------------------\n");
+    description
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::mock::StepRng;
+
+    #[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+    struct Tally {
+        count: u32,
+        cancelled: bool,
+    }
+
+    impl State for Tally {}
+
+    // Counts one up once `ticks` have passed, or fails straight away.
+    #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+    enum Step {
+        Count { ticks: u64 },
+        Fail,
+    }
+
+    impl Action<Tally> for Step {
+        fn act(&self, mut current_state: Tally) -> Tally {
+            current_state.count += 1;
+            current_state
+        }
+
+        fn duration(&self, _current_state: &Tally) -> u64 {
+            match self {
+                Step::Count { ticks } => *ticks,
+                Step::Fail => 1,
+            }
+        }
+
+        fn prerequisite(&self, _current_state: &Tally) -> bool {
+            *self != Step::Fail
+        }
+    }
+
+    impl Executable<Tally> for Step {
+        fn cancel(&self, current_state: &mut Tally) {
+            current_state.cancelled = true;
+        }
+    }
+
+    impl ActionEnum<Tally> for Step {
+        fn generate_available_actions(_current_state: &Tally) -> Vec<Self> {
+            vec![Step::Count { ticks: 1 }]
+        }
+    }
+
+    // Wants the tally to end up just so, at a fixed priority.
+    struct Reach(Tally, i64);
+
+    impl Goal<Tally> for Reach {
+        fn priority(&self, _current_state: &Tally) -> i64 {
+            self.1
+        }
+
+        fn goal_state(&self, _current_state: Tally) -> Tally {
+            self.0.clone()
+        }
+    }
+
+    fn counted(count: u32) -> Reach {
+        Reach(
+            Tally {
+                count,
+                cancelled: false,
+            },
+            1,
+        )
+    }
+
+    // Nothing the planner can do ever cancels, so it counts on until it loses patience.
+    fn never() -> Reach {
+        Reach(
+            Tally {
+                count: 0,
+                cancelled: true,
+            },
+            1,
+        )
+    }
+
+    fn rng() -> StepRng {
+        StepRng::new(0, 1)
+    }
+
+    #[test]
+    fn executor_runs_each_action_for_its_duration() {
+        let mut state = Tally::default();
+        let mut executor = Executor::new(vec![Step::Count { ticks: 2 }, Step::Count { ticks: 1 }]);
+
+        assert_eq!(executor.tick(&mut state, &mut rng()), Status::Running);
+        assert_eq!(state.count, 0);
+        assert_eq!(executor.progress(), Some((&Step::Count { ticks: 2 }, 1)));
+
+        assert_eq!(executor.tick(&mut state, &mut rng()), Status::Running);
+        assert_eq!(state.count, 1);
+
+        assert_eq!(executor.tick(&mut state, &mut rng()), Status::Succeeded);
+        assert_eq!(state.count, 2);
+        assert_eq!(executor.progress(), None);
+    }
+
+    #[test]
+    fn executor_succeeds_straight_away_on_an_empty_plan() {
+        let mut executor = Executor::<Tally, Step>::new(vec![]);
+        assert_eq!(
+            executor.tick(&mut Tally::default(), &mut rng()),
+            Status::Succeeded
+        );
+    }
+
+    #[test]
+    fn executor_drops_the_rest_of_the_plan_when_an_action_fails() {
+        let mut state = Tally::default();
+        let mut executor = Executor::new(vec![Step::Fail, Step::Count { ticks: 1 }]);
+
+        assert_eq!(executor.tick(&mut state, &mut rng()), Status::Failed);
+        assert_eq!(executor.tick(&mut state, &mut rng()), Status::Succeeded);
+        assert_eq!(state.count, 0);
+    }
+
+    #[test]
+    fn executor_cancel_tidies_up_the_action_in_progress() {
+        let mut state = Tally::default();
+        let mut executor = Executor::new(vec![Step::Count { ticks: 3 }, Step::Count { ticks: 1 }]);
+
+        assert_eq!(executor.tick(&mut state, &mut rng()), Status::Running);
+        executor.cancel(&mut state);
+
+        assert!(state.cancelled);
+        assert_eq!(executor.progress(), None);
+        assert_eq!(executor.tick(&mut state, &mut rng()), Status::Succeeded);
+        assert_eq!(state.count, 0);
+    }
+
+    #[test]
+    fn describe_plan_sets_out_each_action() {
+        let description = describe_plan::<Tally, Step>(&[Step::Count { ticks: 2 }, Step::Fail]);
+        assert!(description.contains("Count"));
+        assert!(description.find("ticks: 2") < description.find("Fail"));
+    }
+
+    #[test]
+    fn plan_for_finds_the_way_to_a_reachable_goal() {
+        let plan = plan_for::<Tally, Step>(&Tally::default(), &counted(3));
+        assert_eq!(plan, Some(vec![Step::Count { ticks: 1 }; 3]));
+    }
+
+    #[test]
+    fn planning_gives_up_after_patience() {
+        let mut budget = usize::MAX;
+        let search = Planning::<Tally, Step>::new(&Tally::default(), &never()).search(&mut budget);
+        assert_eq!(search, Search::Unreachable);
+        assert_eq!(usize::MAX - budget, PATIENCE);
+    }
+
+    #[test]
+    fn planning_carries_on_where_it_left_off() {
+        let mut planning = Planning::<Tally, Step>::new(&Tally::default(), &counted(5));
+
+        let mut budget = 2;
+        assert_eq!(planning.search(&mut budget), Search::Pending);
+        assert_eq!(budget, 0);
+
+        let mut budget = 10;
+        assert_eq!(
+            planning.search(&mut budget),
+            Search::Found(vec![Step::Count { ticks: 1 }; 5])
+        );
+        assert_eq!(budget, 7);
+    }
+
+    #[test]
+    fn plan_falls_through_to_the_next_goal_when_one_is_out_of_reach() {
+        let goals: Vec<Box<dyn Goal<Tally>>> =
+            vec![Box::new(counted(1)), Box::new(Reach(never().0, 2))];
+        let plan = plan::<Tally, Step>(Tally::default(), &goals);
+        assert_eq!(plan, Some(vec![Step::Count { ticks: 1 }]));
+    }
+}
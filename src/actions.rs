@@ -1,25 +1,478 @@
-use crate::goap::{Action, ActionEnum, State};
-use crate::item::Item;
-use crate::villager::Villager;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
 
-#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+use crate::item::{Catalogue, Item};
+use crate::movement::{distance, route, route_to_nearest, routes_to_nearest, Route};
+use crate::recipe::Recipe;
+use crate::reservation::Reservations;
+use crate::stockpile::Stockpile;
+use crate::structure::{Blueprint, Building};
+use crate::task::{Byproduct, Task};
+use crate::villager::{Villager, VillagerId, HUNGRY};
+use crate::wear::Wear;
+use crate::wildlife::{Wolf, WolfId, DANGER};
+use crate::world::World;
+use outbound_goap::{Action, ActionEnum, Executable, State, Status};
+use rand::{Rng, RngCore};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub(crate) struct VillageState {
+    pub(crate) world: World,
+    pub(crate) catalogue: Arc<Catalogue>,
+    pub(crate) recipes: Arc<[Recipe]>,
+    pub(crate) blueprints: Arc<[Blueprint]>,
+    // Shared rather than copied into every `Work` action the planner tries.
+    pub(crate) tasks: Arc<[Arc<Task>]>,
+    // The villager doing the planning, and everyone else in the village (sorted by id).
     pub(crate) villager: Villager,
-    pub(crate) items: Vec<Item>,
+    // Everything from here on is shared between states until one of them changes it, which most actions don't, so
+    // the thousands of states a search goes through don't each have a copy to make and free.
+    pub(crate) others: Arc<Vec<Villager>>,
+    pub(crate) items: Arc<Vec<Item>>,
+    pub(crate) stockpile: Stockpile,
+    // Buildings still waiting to be put up, and those that have been.
+    pub(crate) sites: Arc<Vec<Building>>,
+    pub(crate) buildings: Arc<Vec<Building>>,
+    pub(crate) reservations: Arc<Reservations>,
+    pub(crate) wolves: Arc<Vec<Wolf>>,
+}
+
+// The planner hashes every state it comes across, so this leaves out the parts that no action ever changes (the world
+// and everything read from the data files), which would otherwise take up most of the time spent hashing.
+impl Hash for VillageState {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.villager.hash(state);
+        self.others.hash(state);
+        self.items.hash(state);
+        self.stockpile.hash(state);
+        self.sites.hash(state);
+        self.buildings.hash(state);
+        self.reservations.hash(state);
+        self.wolves.hash(state);
+    }
+}
+
+impl VillageState {
+    // Whether the current villager may plan to use `item`, i.e. nobody else has claimed it.
+    pub(crate) fn is_available(&self, item: &Item) -> bool {
+        !self
+            .reservations
+            .is_claimed_by_other(item, self.villager.id)
+    }
+
+    // Make villager `id` the one doing the planning, with everyone else moved to `others`.
+    pub(crate) fn focus(&mut self, id: VillagerId) {
+        if let Some(i) = self.others.iter().position(|other| other.id == id) {
+            let others = Arc::make_mut(&mut self.others);
+            std::mem::swap(&mut self.villager, &mut others[i]);
+            others.sort_unstable_by_key(|other| other.id);
+        }
+    }
+
+    pub(crate) fn other(&self, id: VillagerId) -> Option<&Villager> {
+        self.others.iter().find(|other| other.id == id)
+    }
+
+    fn other_mut(&mut self, id: VillagerId) -> Option<&mut Villager> {
+        Arc::make_mut(&mut self.others)
+            .iter_mut()
+            .find(|other| other.id == id)
+    }
+
+    pub(crate) fn blueprint(&self, name: &str) -> Option<&Blueprint> {
+        self.blueprints.iter().find(|b| b.name == name)
+    }
+
+    // The work to be done on items of kind `target`, if there is any.
+    pub(crate) fn task_on(&self, target: &str) -> Option<&Arc<Task>> {
+        self.tasks.iter().find(|task| task.target == target)
+    }
+
+    // The village as the villager knows it, which leaves out anything lying in parts of the world it hasn't seen.
+    pub(crate) fn perceived(&self) -> Self {
+        let mut perceived = self.clone();
+        let (world, map) = (&self.world, &self.villager.map);
+        Arc::make_mut(&mut perceived.items).retain(|item| map.has_seen(world, item.position));
+        perceived
+    }
+
+    // The nearest wolf close enough to worry the villager, unless it's safe in the village.
+    pub(crate) fn threat(&self) -> Option<&Wolf> {
+        let position = self.villager.position;
+        if self.is_safe() {
+            return None;
+        }
+        self.wolves
+            .iter()
+            .filter(|wolf| distance(position, wolf.position) <= DANGER)
+            .min_by_key(|wolf| distance(position, wolf.position))
+    }
+
+    // Whether the villager is in the village, where no wolf will follow.
+    pub(crate) fn is_safe(&self) -> bool {
+        self.stockpile.contains(self.villager.position)
+    }
+
+    fn has_wolf(&self, id: WolfId) -> bool {
+        self.wolves.iter().any(|wolf| wolf.id == id)
+    }
+
+    // Seedlings planted in the world that have yet to come up.
+    pub(crate) fn planted(&self) -> usize {
+        self.items
+            .iter()
+            .filter(|item| self.catalogue.is_seedling(&item.id))
+            .count()
+    }
+
+    // Whether the villager has anything to plant.
+    pub(crate) fn has_seedling(&self) -> bool {
+        self.villager
+            .inventory
+            .iter()
+            .any(|id| self.catalogue.is_seedling(id))
+    }
+
+    // Something to plant put by in the stockpile, if there is anything.
+    pub(crate) fn stocked_seedling(&self) -> Option<&str> {
+        self.stockpile
+            .contents
+            .iter()
+            .map(String::as_str)
+            .find(|id| self.catalogue.is_seedling(id))
+    }
 }
 
 impl State for VillageState {
+    // Met once the villager has at least everything the goal asks for, and any wolf it wants gone is gone (or the
+    // villager is safely back in the village, if that will do).
     fn compare(&self, other_state: &Self) -> bool {
-        self.villager.inventory == other_state.villager.inventory
+        carries(&self.villager.inventory, &other_state.villager.inventory)
+            && other_state.others.iter().all(|wanted| {
+                self.other(wanted.id)
+                    .is_some_and(|other| carries(&other.inventory, &wanted.inventory))
+            })
+            && carries(&self.stockpile.contents, &other_state.stockpile.contents)
+            && self.buildings.len() >= other_state.buildings.len()
+            && self.planted() >= other_state.planted()
+            && self.villager.satiety >= other_state.villager.satiety
+            && self.villager.map.regions_seen() >= other_state.villager.map.regions_seen()
+            && (self.wolves.iter().all(|wolf| other_state.has_wolf(wolf.id))
+                || other_state.is_safe() && self.is_safe())
+    }
+
+    // The longest single errand still to run, which no plan can beat.
+    fn estimate(&self, goal_state: &Self) -> u64 {
+        let short_carried = shortfall(&self.villager.inventory, &goal_state.villager.inventory);
+        let short_stocked = shortfall(&self.stockpile.contents, &goal_state.stockpile.contents);
+
+        let position = self.villager.position;
+        let to_stockpile = |from| self.stockpile.distance(from) + 1;
+
+        let carried = short_carried.into_iter().map(|(id, short)| {
+            let fetch = self.fetch_estimate(&id, |source| distance(position, source) + 1);
+            if self.stockpile.count(&id) >= short {
+                fetch.min(to_stockpile(position))
+            } else {
+                fetch
+            }
+        });
+        let stocked = short_stocked.into_iter().map(|(id, short)| {
+            let carrying = self.villager.inventory.iter().filter(|i| **i == id).count();
+            if carrying >= short {
+                to_stockpile(position)
+            } else {
+                self.fetch_estimate(&id, |source| {
+                    distance(position, source) + 1 + to_stockpile(source)
+                })
+            }
+        });
+
+        let given = goal_state.others.iter().flat_map(|wanted| {
+            let Some(other) = self.other(wanted.id) else {
+                return vec![];
+            };
+            let to_them = |from| distance(from, other.position) + 1;
+            shortfall(&other.inventory, &wanted.inventory)
+                .into_iter()
+                .map(|(id, short)| {
+                    if self.villager.count(&id) >= short {
+                        to_them(position)
+                    } else {
+                        self.fetch_estimate(&id, |source| {
+                            distance(position, source) + 1 + to_them(source)
+                        })
+                    }
+                })
+                .collect()
+        });
+
+        let built = (self.buildings.len() < goal_state.buildings.len())
+            .then(|| {
+                self.sites
+                    .iter()
+                    .filter_map(|site| {
+                        let blueprint = self.blueprint(&site.blueprint)?;
+                        let missing: usize =
+                            shortfall(&self.villager.inventory, &blueprint.materials)
+                                .iter()
+                                .map(|(_, short)| short)
+                                .sum();
+                        Some(distance(position, site.corner) + missing as u64 + blueprint.duration)
+                    })
+                    .min()
+            })
+            .flatten();
+
+        let planted = (self.planted() < goal_state.planted()).then(|| {
+            let fetch = if self.has_seedling() {
+                0
+            } else if self.stocked_seedling().is_some() {
+                to_stockpile(position)
+            } else {
+                self.catalogue
+                    .kinds()
+                    .iter()
+                    .filter(|kind| self.catalogue.is_seedling(&kind.id))
+                    .map(|kind| self.fetch_estimate(&kind.id, |at| distance(position, at)))
+                    .min()
+                    .unwrap_or(0)
+            };
+            fetch + PLANTING_DURATION
+        });
+
+        // Seeing somewhere new takes a walk at the very least.
+        let explored = (self.villager.map.regions_seen() < goal_state.villager.map.regions_seen())
+            .then_some(1);
+
+        let safe = self
+            .wolves
+            .iter()
+            .filter(|wolf| !goal_state.has_wolf(wolf.id))
+            .map(|wolf| {
+                let fight = distance(position, wolf.position).saturating_sub(1) + ATTACK_DURATION;
+                if goal_state.is_safe() {
+                    fight.min(self.stockpile.distance(position))
+                } else {
+                    fight
+                }
+            })
+            .max();
+
+        carried
+            .chain(stocked)
+            .chain(given)
+            .chain(built)
+            .chain(planted)
+            .chain(explored)
+            .chain(safe)
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+// How many recipes deep the planner's estimates look for ways to craft something, which stops recipes that go round in
+// circles from going on forever.
+const CRAFTING_DEPTH: u32 = 3;
+
+impl VillageState {
+    // Whether `other` has an `id` to spare and there's something the villager could give for it.
+    fn could_trade_for(&self, other: &Villager, id: &str) -> bool {
+        other.can_spare(id)
+            && self.villager.count(id) == 0
+            && kinds(&self.villager.inventory)
+                .iter()
+                .any(|give| self.villager.can_spare(give) && other.count(give) == 0)
+    }
+
+    // The cheapest `errand` via somewhere `id` can be had from, counting the work of getting it out of whatever it's
+    // worked from, or of crafting it. Anything with nowhere to come from can't be got at all, so says nothing.
+    fn fetch_estimate(&self, id: &str, errand: impl Fn((i64, i64)) -> u64) -> u64 {
+        self.fetch_within(id, &errand, CRAFTING_DEPTH)
+    }
+
+    // As `fetch_estimate`, looking no more than `depth` recipes deep for ways to craft it.
+    fn fetch_within(&self, id: &str, errand: &dyn Fn((i64, i64)) -> u64, depth: u32) -> u64 {
+        let found = self
+            .items
+            .iter()
+            .filter(|item| item.amount > 0)
+            .filter_map(|item| {
+                let work = if item.id == id {
+                    0
+                } else {
+                    let task = self.task_on(&item.id).filter(|task| task.produces(id))?;
+                    task.quickest()
+                };
+                Some(errand(item.position) + work)
+            })
+            .chain(
+                self.others
+                    .iter()
+                    .filter(|other| self.could_trade_for(other, id))
+                    .map(|other| errand(other.position)),
+            );
+
+        let crafted = self
+            .recipes
+            .iter()
+            .filter(|recipe| depth > 0 && recipe.outputs.iter().any(|output| output == id))
+            .filter_map(|recipe| self.craft_estimate(recipe, errand, depth - 1));
+
+        found.chain(crafted).min().unwrap_or(0)
+    }
+
+    // Getting hold of whatever `recipe` needs that the villager hasn't got, crafting it (at the nearest station, if it
+    // needs one) and going on with `errand` from there. `None` if there's no station to be had.
+    fn craft_estimate(
+        &self,
+        recipe: &Recipe,
+        errand: &dyn Fn((i64, i64)) -> u64,
+        depth: u32,
+    ) -> Option<u64> {
+        let position = self.villager.position;
+        let inputs: u64 = shortfall(&self.villager.inventory, &recipe.inputs)
+            .into_iter()
+            .map(|(input, short)| {
+                let fetch =
+                    self.fetch_within(&input, &|source| distance(position, source) + 1, depth);
+                let fetch = if self.stockpile.count(&input) > 0 {
+                    fetch.min(self.stockpile.distance(position) + 1)
+                } else {
+                    fetch
+                };
+                // Any more of the same are likely to be had from the same place.
+                fetch + short as u64 - 1
+            })
+            .sum();
+
+        let crafting = match &recipe.station {
+            None => errand(position),
+            Some(station) => self
+                .items
+                .iter()
+                .filter(|item| &item.id == station)
+                .map(|item| errand(item.position))
+                .min()?,
+        };
+        Some(inputs + crafting + recipe.duration)
+    }
+}
+
+// Each item `wanted` has more of than `inventory`, and how many more.
+fn shortfall(inventory: &[String], wanted: &[String]) -> Vec<(String, usize)> {
+    let carried = count_items(inventory);
+    count_items(wanted)
+        .into_iter()
+        .filter_map(|(id, count)| {
+            let short = count.saturating_sub(carried.get(id).copied().unwrap_or(0));
+            (short > 0).then(|| (id.to_string(), short))
+        })
+        .collect()
+}
+
+// Inventories are kept sorted so that carrying the same things always makes for the same state, however they were
+// come by.
+fn stash(inventory: &mut Vec<String>, id: String) {
+    let i = inventory.partition_point(|held| held < &id);
+    inventory.insert(i, id);
+}
+
+// Stashes an `id` come from somewhere else, bringing its `uses` of wear along with it.
+fn stash_worn(inventory: &mut Vec<String>, wear: &mut Wear, id: String, uses: u32) {
+    wear.put(&id, uses);
+    stash(inventory, id);
+}
+
+// Takes one `id` out of `inventory` to go somewhere else, along with the wear on it. If there's a choice the freshest
+// goes.
+fn unstash(inventory: &mut Vec<String>, wear: &mut Wear, id: &str) -> Option<u32> {
+    let i = inventory.iter().position(|held| held == id)?;
+    let held = inventory.iter().filter(|held| *held == id).count();
+    inventory.remove(i);
+    Some(wear.take(id, held))
+}
+
+// Whether `inventory` holds every one of `wanted`, duplicates included.
+fn carries(inventory: &[String], wanted: &[String]) -> bool {
+    let carried = count_items(inventory);
+    count_items(wanted)
+        .iter()
+        .all(|(id, wanted)| carried.get(id).is_some_and(|count| count >= wanted))
+}
+
+fn count_items(inventory: &[String]) -> HashMap<&str, usize> {
+    let mut counts = HashMap::new();
+    for id in inventory {
+        *counts.entry(id.as_str()).or_default() += 1;
     }
+    counts
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub(crate) enum VillagerActionEnum {
     MoveToNearestItem(MoveToNearestItem),
     Move(Move),
-    ChopTree(ChopTree),
+    Work(Work),
     PickUpItem(PickUpItem),
+    Craft(Craft),
+    Eat(Eat),
+    Deposit(Deposit),
+    Withdraw(Withdraw),
+    Construct(Construct),
+    Plant(Plant),
+    Give(Give),
+    Trade(Trade),
+    DropItem(DropItem),
+    ExploreFrontier(ExploreFrontier),
+    Attack(Attack),
+    Flee(Flee),
+}
+
+impl VillagerActionEnum {
+    // What the villager is up to while carrying the action out.
+    pub(crate) fn activity(&self) -> &str {
+        match self {
+            VillagerActionEnum::MoveToNearestItem(_) | VillagerActionEnum::Move(_) => "walking",
+            VillagerActionEnum::Work(a) => &a.task.name,
+            VillagerActionEnum::PickUpItem(_) => "picking up",
+            VillagerActionEnum::Craft(_) => "crafting",
+            VillagerActionEnum::Eat(_) => "eating",
+            VillagerActionEnum::Deposit(_) => "depositing",
+            VillagerActionEnum::Withdraw(_) => "withdrawing",
+            VillagerActionEnum::Construct(_) => "building",
+            VillagerActionEnum::Plant(_) => "planting",
+            VillagerActionEnum::Give(_) => "giving",
+            VillagerActionEnum::Trade(_) => "trading",
+            VillagerActionEnum::DropItem(_) => "dropping",
+            VillagerActionEnum::ExploreFrontier(_) => "exploring",
+            VillagerActionEnum::Attack(_) => "fighting",
+            VillagerActionEnum::Flee(_) => "fleeing",
+        }
+    }
+
+    // The world item this action uses up, if any.
+    pub(crate) fn target_item(&self) -> Option<&Item> {
+        match self {
+            VillagerActionEnum::Work(a) => Some(&a.item),
+            VillagerActionEnum::PickUpItem(a) => Some(&a.item),
+            VillagerActionEnum::MoveToNearestItem(_)
+            | VillagerActionEnum::Move(_)
+            | VillagerActionEnum::Craft(_)
+            | VillagerActionEnum::Eat(_)
+            | VillagerActionEnum::Deposit(_)
+            | VillagerActionEnum::Withdraw(_)
+            | VillagerActionEnum::Construct(_)
+            | VillagerActionEnum::Plant(_)
+            | VillagerActionEnum::Give(_)
+            | VillagerActionEnum::Trade(_)
+            | VillagerActionEnum::DropItem(_)
+            | VillagerActionEnum::ExploreFrontier(_)
+            | VillagerActionEnum::Attack(_)
+            | VillagerActionEnum::Flee(_) => None,
+        }
+    }
 }
 
 impl ActionEnum<VillageState> for VillagerActionEnum {
@@ -28,39 +481,123 @@ impl ActionEnum<VillageState> for VillagerActionEnum {
 
         let (agent_x, agent_y) = current_state.villager.position;
 
-        // Needed to allow agent to return to start point.
-        available_actions.push(Self::Move(Move {
-            delta_x: { -agent_x },
-            delta_y: { -agent_y },
-        }));
+        // Needed to allow agent to return to the village stockpile.
+        if !current_state
+            .stockpile
+            .contains(current_state.villager.position)
+        {
+            if let Some(action) = Move::to(current_state.stockpile.centre(), current_state) {
+                available_actions.push(Self::Move(action));
+            }
+        }
 
-        let tree_action = Self::MoveToNearestItem(MoveToNearestItem {
-            target_item_id: "tree".to_string(),
-        });
-        if tree_action.prerequisite(current_state) {
-            available_actions.push(tree_action);
+        // Off to see the neighbours when there's anything to hand over or swap with them, and doing so once there.
+        for other in current_state.others.iter() {
+            let gifts = Give::candidates(current_state, other);
+            let trades = Trade::candidates(current_state, other);
+            if gifts.is_empty() && trades.is_empty() {
+                continue;
+            }
+            if !near(current_state, other) {
+                available_actions.extend(Move::to(other.position, current_state).map(Self::Move));
+                continue;
+            }
+            available_actions.extend(gifts.into_iter().map(Self::Give));
+            available_actions.extend(trades.into_iter().map(Self::Trade));
         }
 
-        let stone_action = Self::MoveToNearestItem(MoveToNearestItem {
-            target_item_id: "stone".to_string(),
-        });
-        if stone_action.prerequisite(current_state) {
-            available_actions.push(stone_action);
+        // Off to build something, once there's everything needed for it.
+        for site in current_state.sites.iter() {
+            let action = Construct { site: site.clone() };
+            if action.has_materials(current_state) {
+                if current_state.villager.position != site.corner {
+                    available_actions.extend(Move::to(site.corner, current_state).map(Self::Move));
+                } else if action.prerequisite(current_state) {
+                    available_actions.push(Self::Construct(action));
+                }
+            }
         }
 
-        let berry_action = Self::MoveToNearestItem(MoveToNearestItem {
-            target_item_id: "berry".to_string(),
-        });
-        if berry_action.prerequisite(current_state) {
-            available_actions.push(berry_action);
+        // Anything there's work to be done on or lying about to be picked up, plus anywhere the recipes need doing.
+        let mut targets: Vec<&str> = current_state
+            .tasks
+            .iter()
+            .map(|task| task.target.as_str())
+            .chain(
+                current_state
+                    .items
+                    .iter()
+                    .map(|item| item.id.as_str())
+                    .filter(|id| current_state.catalogue.is_pickable(id)),
+            )
+            .chain(
+                current_state
+                    .recipes
+                    .iter()
+                    .filter_map(|r| r.station.as_deref()),
+            )
+            .collect();
+        targets.sort_unstable();
+        targets.dedup();
+        available_actions.extend(
+            MoveToNearestItem::towards(&targets, current_state)
+                .into_iter()
+                .map(Self::MoveToNearestItem),
+        );
+
+        available_actions
+            .extend(ExploreFrontier::nearest(current_state).map(Self::ExploreFrontier));
+
+        // Standing up to whatever wolf is closing in, or running from it.
+        if let Some(wolf) = current_state.threat() {
+            let action = Attack { wolf: wolf.id };
+            if action.prerequisite(current_state) {
+                available_actions.push(Self::Attack(action));
+            } else {
+                available_actions.extend(Move::to(wolf.position, current_state).map(Self::Move));
+            }
+            available_actions.extend(Flee::to_village(current_state).map(Self::Flee));
+        }
+
+        for recipe in current_state.recipes.iter() {
+            let action = Craft {
+                recipe: recipe.clone(),
+            };
+            if action.prerequisite(current_state) {
+                available_actions.push(Self::Craft(action));
+            }
         }
 
-        for item in current_state.items.clone() {
+        available_actions.extend(Eat::from_inventory(current_state).map(Self::Eat));
+
+        available_actions.extend(Plant::available(current_state));
+
+        available_actions.extend(
+            DropItem::available(current_state)
+                .into_iter()
+                .map(Self::DropItem),
+        );
+
+        available_actions.extend(
+            Deposit::available(current_state)
+                .into_iter()
+                .map(Self::Deposit),
+        );
+        available_actions.extend(
+            Withdraw::available(current_state)
+                .into_iter()
+                .map(Self::Withdraw),
+        );
+
+        for item in current_state.items.iter() {
             if item.position == (agent_x, agent_y) {
-                if item.id == *"tree" {
-                    let action = ChopTree { item: item.clone() };
+                if let Some(task) = current_state.task_on(&item.id) {
+                    let action = Work {
+                        task: task.clone(),
+                        item: item.clone(),
+                    };
                     if action.prerequisite(current_state) {
-                        available_actions.push(Self::ChopTree(action));
+                        available_actions.push(Self::Work(action));
                     }
                 } else {
                     let action = PickUpItem { item: item.clone() };
@@ -82,17 +619,20 @@ impl Action<VillageState> for VillagerActionEnum {
         match self {
             VillagerActionEnum::MoveToNearestItem(a) => a.act(current_state),
             VillagerActionEnum::Move(a) => a.act(current_state),
-            VillagerActionEnum::ChopTree(a) => a.act(current_state),
+            VillagerActionEnum::Work(a) => a.act(current_state),
             VillagerActionEnum::PickUpItem(a) => a.act(current_state),
-        }
-    }
-
-    fn cost(&self) -> u64 {
-        match self {
-            VillagerActionEnum::MoveToNearestItem(a) => a.cost(),
-            VillagerActionEnum::Move(a) => a.cost(),
-            VillagerActionEnum::ChopTree(a) => a.cost(),
-            VillagerActionEnum::PickUpItem(a) => a.cost(),
+            VillagerActionEnum::Craft(a) => a.act(current_state),
+            VillagerActionEnum::Eat(a) => a.act(current_state),
+            VillagerActionEnum::Deposit(a) => a.act(current_state),
+            VillagerActionEnum::Withdraw(a) => a.act(current_state),
+            VillagerActionEnum::Construct(a) => a.act(current_state),
+            VillagerActionEnum::Plant(a) => a.act(current_state),
+            VillagerActionEnum::Give(a) => a.act(current_state),
+            VillagerActionEnum::Trade(a) => a.act(current_state),
+            VillagerActionEnum::DropItem(a) => a.act(current_state),
+            VillagerActionEnum::ExploreFrontier(a) => a.act(current_state),
+            VillagerActionEnum::Attack(a) => a.act(current_state),
+            VillagerActionEnum::Flee(a) => a.act(current_state),
         }
     }
 
@@ -100,8 +640,185 @@ impl Action<VillageState> for VillagerActionEnum {
         match self {
             VillagerActionEnum::MoveToNearestItem(a) => a.prerequisite(current_state),
             VillagerActionEnum::Move(a) => a.prerequisite(current_state),
-            VillagerActionEnum::ChopTree(a) => a.prerequisite(current_state),
+            VillagerActionEnum::Work(a) => a.prerequisite(current_state),
             VillagerActionEnum::PickUpItem(a) => a.prerequisite(current_state),
+            VillagerActionEnum::Craft(a) => a.prerequisite(current_state),
+            VillagerActionEnum::Eat(a) => a.prerequisite(current_state),
+            VillagerActionEnum::Deposit(a) => a.prerequisite(current_state),
+            VillagerActionEnum::Withdraw(a) => a.prerequisite(current_state),
+            VillagerActionEnum::Construct(a) => a.prerequisite(current_state),
+            VillagerActionEnum::Plant(a) => a.prerequisite(current_state),
+            VillagerActionEnum::Give(a) => a.prerequisite(current_state),
+            VillagerActionEnum::Trade(a) => a.prerequisite(current_state),
+            VillagerActionEnum::DropItem(a) => a.prerequisite(current_state),
+            VillagerActionEnum::ExploreFrontier(a) => a.prerequisite(current_state),
+            VillagerActionEnum::Attack(a) => a.prerequisite(current_state),
+            VillagerActionEnum::Flee(a) => a.prerequisite(current_state),
+        }
+    }
+
+    fn duration(&self, current_state: &VillageState) -> u64 {
+        match self {
+            VillagerActionEnum::MoveToNearestItem(a) => a.duration(current_state),
+            VillagerActionEnum::Move(a) => a.duration(current_state),
+            VillagerActionEnum::Work(a) => a.duration(current_state),
+            VillagerActionEnum::PickUpItem(a) => a.duration(current_state),
+            VillagerActionEnum::Craft(a) => a.duration(current_state),
+            VillagerActionEnum::Eat(a) => a.duration(current_state),
+            VillagerActionEnum::Deposit(a) => a.duration(current_state),
+            VillagerActionEnum::Withdraw(a) => a.duration(current_state),
+            VillagerActionEnum::Construct(a) => a.duration(current_state),
+            VillagerActionEnum::Plant(a) => a.duration(current_state),
+            VillagerActionEnum::Give(a) => a.duration(current_state),
+            VillagerActionEnum::Trade(a) => a.duration(current_state),
+            VillagerActionEnum::DropItem(a) => a.duration(current_state),
+            VillagerActionEnum::ExploreFrontier(a) => a.duration(current_state),
+            VillagerActionEnum::Attack(a) => a.duration(current_state),
+            VillagerActionEnum::Flee(a) => a.duration(current_state),
+        }
+    }
+}
+
+impl Executable<VillageState> for VillagerActionEnum {
+    fn execute(
+        &self,
+        current_state: &mut VillageState,
+        elapsed: u64,
+        rng: &mut dyn RngCore,
+    ) -> Status {
+        match self {
+            VillagerActionEnum::MoveToNearestItem(a) => a.execute(current_state, elapsed, rng),
+            VillagerActionEnum::Move(a) => a.execute(current_state, elapsed, rng),
+            VillagerActionEnum::Work(a) => a.execute(current_state, elapsed, rng),
+            VillagerActionEnum::PickUpItem(a) => a.execute(current_state, elapsed, rng),
+            VillagerActionEnum::Craft(a) => a.execute(current_state, elapsed, rng),
+            VillagerActionEnum::Eat(a) => a.execute(current_state, elapsed, rng),
+            VillagerActionEnum::Deposit(a) => a.execute(current_state, elapsed, rng),
+            VillagerActionEnum::Withdraw(a) => a.execute(current_state, elapsed, rng),
+            VillagerActionEnum::Construct(a) => a.execute(current_state, elapsed, rng),
+            VillagerActionEnum::Plant(a) => a.execute(current_state, elapsed, rng),
+            VillagerActionEnum::Give(a) => a.execute(current_state, elapsed, rng),
+            VillagerActionEnum::Trade(a) => a.execute(current_state, elapsed, rng),
+            VillagerActionEnum::DropItem(a) => a.execute(current_state, elapsed, rng),
+            VillagerActionEnum::ExploreFrontier(a) => a.execute(current_state, elapsed, rng),
+            VillagerActionEnum::Attack(a) => a.execute(current_state, elapsed, rng),
+            VillagerActionEnum::Flee(a) => a.execute(current_state, elapsed, rng),
+        }
+    }
+
+    fn outcome(&self, current_state: &mut VillageState, rng: &mut dyn RngCore) -> Status {
+        match self {
+            VillagerActionEnum::MoveToNearestItem(a) => a.outcome(current_state, rng),
+            VillagerActionEnum::Move(a) => a.outcome(current_state, rng),
+            VillagerActionEnum::Work(a) => a.outcome(current_state, rng),
+            VillagerActionEnum::PickUpItem(a) => a.outcome(current_state, rng),
+            VillagerActionEnum::Craft(a) => a.outcome(current_state, rng),
+            VillagerActionEnum::Eat(a) => a.outcome(current_state, rng),
+            VillagerActionEnum::Deposit(a) => a.outcome(current_state, rng),
+            VillagerActionEnum::Withdraw(a) => a.outcome(current_state, rng),
+            VillagerActionEnum::Construct(a) => a.outcome(current_state, rng),
+            VillagerActionEnum::Plant(a) => a.outcome(current_state, rng),
+            VillagerActionEnum::Give(a) => a.outcome(current_state, rng),
+            VillagerActionEnum::Trade(a) => a.outcome(current_state, rng),
+            VillagerActionEnum::DropItem(a) => a.outcome(current_state, rng),
+            VillagerActionEnum::ExploreFrontier(a) => a.outcome(current_state, rng),
+            VillagerActionEnum::Attack(a) => a.outcome(current_state, rng),
+            VillagerActionEnum::Flee(a) => a.outcome(current_state, rng),
+        }
+    }
+
+    fn cancel(&self, current_state: &mut VillageState) {
+        match self {
+            VillagerActionEnum::MoveToNearestItem(a) => a.cancel(current_state),
+            VillagerActionEnum::Move(a) => a.cancel(current_state),
+            VillagerActionEnum::Work(a) => a.cancel(current_state),
+            VillagerActionEnum::PickUpItem(a) => a.cancel(current_state),
+            VillagerActionEnum::Craft(a) => a.cancel(current_state),
+            VillagerActionEnum::Eat(a) => a.cancel(current_state),
+            VillagerActionEnum::Deposit(a) => a.cancel(current_state),
+            VillagerActionEnum::Withdraw(a) => a.cancel(current_state),
+            VillagerActionEnum::Construct(a) => a.cancel(current_state),
+            VillagerActionEnum::Plant(a) => a.cancel(current_state),
+            VillagerActionEnum::Give(a) => a.cancel(current_state),
+            VillagerActionEnum::Trade(a) => a.cancel(current_state),
+            VillagerActionEnum::DropItem(a) => a.cancel(current_state),
+            VillagerActionEnum::ExploreFrontier(a) => a.cancel(current_state),
+            VillagerActionEnum::Attack(a) => a.cancel(current_state),
+            VillagerActionEnum::Flee(a) => a.cancel(current_state),
+        }
+    }
+}
+
+// Walks a route worked out beforehand, the heavier the villager's load the slower. The other movement actions are
+// walks with a purpose, and leave the walking itself to this.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct Move {
+    route: Route,
+}
+
+impl Move {
+    fn to(destination: (i64, i64), current_state: &VillageState) -> Option<Self> {
+        let route = route(
+            &current_state.world,
+            current_state.villager.position,
+            destination,
+        )?;
+        Some(Self { route })
+    }
+
+    // The way to whichever of `targets` is quickest to reach, if any of them can be.
+    fn to_nearest(targets: HashSet<(i64, i64)>, current_state: &VillageState) -> Option<Self> {
+        let route = routes_to_nearest(
+            &current_state.world,
+            current_state.villager.position,
+            &[targets],
+        )
+        .pop()
+        .flatten()?;
+        Some(Self { route })
+    }
+}
+
+impl Action<VillageState> for Move {
+    fn act(&self, current_state: VillageState) -> VillageState {
+        let mut new_state = current_state.clone();
+        new_state.villager.position = self.route.destination();
+        new_state
+    }
+
+    fn duration(&self, current_state: &VillageState) -> u64 {
+        current_state
+            .villager
+            .laden(&current_state.catalogue, self.route.cost)
+    }
+
+    fn prerequisite(&self, current_state: &VillageState) -> bool {
+        current_state.villager.position == self.route.start()
+    }
+}
+
+impl Executable<VillageState> for Move {
+    fn execute(
+        &self,
+        current_state: &mut VillageState,
+        elapsed: u64,
+        _rng: &mut dyn RngCore,
+    ) -> Status {
+        let route = &self.route;
+        let spent = elapsed + 1;
+        let (villager, catalogue) = (&current_state.villager, &current_state.catalogue);
+        let position =
+            route.position_after(&current_state.world, villager.unladen(catalogue, spent));
+        if elapsed == 0 && villager.position != route.start() {
+            return Status::Failed;
+        }
+
+        let done = spent >= villager.laden(catalogue, route.cost);
+        current_state.villager.position = position;
+        if done {
+            Status::Succeeded
+        } else {
+            Status::Running
         }
     }
 }
@@ -109,88 +826,188 @@ impl Action<VillageState> for VillagerActionEnum {
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub(crate) struct MoveToNearestItem {
     target_item_id: String,
+    walk: Move,
 }
 
 impl MoveToNearestItem {
-    fn get_new_position(&self, current_state: &VillageState) -> Option<(i64, i64)> {
-        let (current_x, current_y) = current_state.villager.position;
-        current_state
-            .items
+    // Works out the way to the nearest reachable item of each of the given ids, skipping ids with nothing in reach.
+    fn towards(target_item_ids: &[&str], current_state: &VillageState) -> Vec<Self> {
+        let targets: Vec<HashSet<(i64, i64)>> = target_item_ids
+            .iter()
+            .map(|&target_item_id| {
+                current_state
+                    .items
+                    .iter()
+                    .filter(|item| {
+                        item.id == target_item_id
+                            && item.amount > 0
+                            && current_state.is_available(item)
+                    })
+                    .map(|item| item.position)
+                    .collect()
+            })
+            .collect();
+
+        let routes = routes_to_nearest(
+            &current_state.world,
+            current_state.villager.position,
+            &targets,
+        );
+
+        target_item_ids
             .iter()
-            .filter_map(|item| (item.id == self.target_item_id).then_some(item.position))
-            .min_by_key(|(ix, iy)| {
-                (((ix - current_x) as f64).powf(2.0) + ((iy - current_y) as f64).powf(2.0)).sqrt()
-                    as u64
+            .zip(routes)
+            .filter_map(|(&target_item_id, route)| {
+                Some(Self {
+                    target_item_id: target_item_id.to_string(),
+                    walk: Move { route: route? },
+                })
             })
+            .collect()
+    }
+
+    fn target_is_there(&self, current_state: &VillageState) -> bool {
+        current_state.items.iter().any(|item| {
+            item.id == self.target_item_id
+                && item.position == self.walk.route.destination()
+                && item.amount > 0
+                && current_state.is_available(item)
+        })
     }
 }
 
 impl Action<VillageState> for MoveToNearestItem {
     fn act(&self, current_state: VillageState) -> VillageState {
-        let mut new_state = current_state.clone();
-        new_state.villager.position = self
-            .get_new_position(&current_state)
-            .expect("If we passed prerequisite there should be something here!");
-        new_state
+        self.walk.act(current_state)
     }
 
-    fn cost(&self) -> u64 {
-        // TODO
-        5
+    fn duration(&self, current_state: &VillageState) -> u64 {
+        self.walk.duration(current_state)
     }
 
     fn prerequisite(&self, current_state: &VillageState) -> bool {
-        // Check that new position is not out of bounds
-        const WORLD_MAX: i64 = 151;
+        self.walk.prerequisite(current_state) && self.target_is_there(current_state)
+    }
+}
 
-        if let Some(new_position) = self.get_new_position(current_state) {
-            new_position.0 > -1
-                && new_position.0 < WORLD_MAX
-                && new_position.1 > -1
-                && new_position.1 < WORLD_MAX
-        } else {
-            false
+impl Executable<VillageState> for MoveToNearestItem {
+    // Gives up if the item goes (or gets claimed) on the way.
+    fn execute(
+        &self,
+        current_state: &mut VillageState,
+        elapsed: u64,
+        rng: &mut dyn RngCore,
+    ) -> Status {
+        if !self.target_is_there(current_state) {
+            return Status::Failed;
         }
+        self.walk.execute(current_state, elapsed, rng)
     }
 }
 
+// Runs for the nearest edge of the village, where no wolf will follow.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub(crate) struct Move {
-    delta_x: i64,
-    delta_y: i64,
+pub(crate) struct Flee {
+    walk: Move,
 }
 
-impl Move {
-    fn get_new_position(&self, current_state: &VillageState) -> (i64, i64) {
-        let (current_x, current_y) = current_state.villager.position;
-        (current_x + self.delta_x, current_y + self.delta_y)
+impl Flee {
+    fn to_village(current_state: &VillageState) -> Option<Self> {
+        let stockpile = &current_state.stockpile;
+        let (left, top) = stockpile.corner;
+        let village = (left..left + stockpile.width)
+            .flat_map(|x| (top..top + stockpile.height).map(move |y| (x, y)))
+            .collect();
+        let walk = Move::to_nearest(village, current_state)?;
+        Some(Self { walk })
     }
 }
 
-impl Action<VillageState> for Move {
+impl Action<VillageState> for Flee {
     fn act(&self, current_state: VillageState) -> VillageState {
-        let mut new_state = current_state.clone();
-        new_state.villager.position = self.get_new_position(&current_state);
+        self.walk.act(current_state)
+    }
+
+    fn duration(&self, current_state: &VillageState) -> u64 {
+        self.walk.duration(current_state)
+    }
+
+    fn prerequisite(&self, current_state: &VillageState) -> bool {
+        self.walk.prerequisite(current_state)
+    }
+}
+
+impl Executable<VillageState> for Flee {
+    fn execute(
+        &self,
+        current_state: &mut VillageState,
+        elapsed: u64,
+        rng: &mut dyn RngCore,
+    ) -> Status {
+        self.walk.execute(current_state, elapsed, rng)
+    }
+}
+
+// Heads for the nearest bit of the world the villager hasn't seen yet, taking it in on arrival.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct ExploreFrontier {
+    walk: Move,
+}
+
+impl ExploreFrontier {
+    fn nearest(current_state: &VillageState) -> Option<Self> {
+        let frontier = current_state
+            .villager
+            .map
+            .frontier(&current_state.world)
+            .into_iter()
+            .collect();
+        let walk = Move::to_nearest(frontier, current_state)?;
+        Some(Self { walk })
+    }
+}
+
+impl Action<VillageState> for ExploreFrontier {
+    // There's no knowing what will turn up, so all the planner can count on is having seen more of the world.
+    fn act(&self, current_state: VillageState) -> VillageState {
+        let mut new_state = self.walk.act(current_state);
+        let destination = new_state.villager.position;
+        new_state.villager.map.look(&new_state.world, destination);
         new_state
     }
 
-    fn cost(&self) -> u64 {
-        let fdelta_x: f64 = self.delta_x as f64;
-        let fdelta_y: f64 = self.delta_y as f64;
-        (fdelta_x.powf(2.0) + fdelta_y.powf(2.0)).sqrt() as u64
+    fn duration(&self, current_state: &VillageState) -> u64 {
+        self.walk.duration(current_state)
     }
 
     fn prerequisite(&self, current_state: &VillageState) -> bool {
-        // Check that new position is not out of bounds
-        let new_position = self.get_new_position(current_state);
-        const WORLD_MAX: i64 = 26;
-        new_position.0 > -1
-            && new_position.0 < WORLD_MAX
-            && new_position.1 > -1
-            && new_position.1 < WORLD_MAX
+        self.walk.prerequisite(current_state)
+    }
+}
+
+impl Executable<VillageState> for ExploreFrontier {
+    // The villager looks about as it goes, so there's nothing more to do on arrival.
+    fn execute(
+        &self,
+        current_state: &mut VillageState,
+        elapsed: u64,
+        rng: &mut dyn RngCore,
+    ) -> Status {
+        self.walk.execute(current_state, elapsed, rng)
     }
 }
 
+// What the planner counts on a yield of anywhere between `least` and `most` giving.
+fn expected(least: usize, most: usize) -> usize {
+    (least + most) / 2
+}
+
+// Ticks an action can be expected to take, allowing for it only coming off `chance` (out of) of the time and having
+// to be tried again.
+fn expected_ticks(duration: u64, (chance, out_of): (u32, u32)) -> u64 {
+    (duration * out_of as u64).div_ceil(chance as u64)
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub(crate) struct PickUpItem {
     item: Item,
@@ -199,52 +1016,833 @@ pub(crate) struct PickUpItem {
 impl Action<VillageState> for PickUpItem {
     fn act(&self, current_state: VillageState) -> VillageState {
         let mut new_state = current_state.clone();
-        new_state.villager.inventory.push(self.item.id.clone());
+        let villager = &mut new_state.villager;
+        stash_worn(
+            &mut villager.inventory,
+            &mut villager.wear,
+            self.item.id.clone(),
+            self.item.wear,
+        );
 
         if let Some(i) = new_state.items.iter().position(|item| item == &self.item) {
-            new_state.items.remove(i);
+            Arc::make_mut(&mut new_state.items).remove(i);
         }
 
         new_state
     }
 
-    fn cost(&self) -> u64 {
-        // Arbitrary cost to pick up an item. Maybe this should be weight?
-        1
-    }
-
+    // Anything there's work to be done on has to be worked rather than just picked up.
     fn prerequisite(&self, current_state: &VillageState) -> bool {
-        current_state.villager.position == self.item.position && self.item.id != *"tree"
+        current_state.villager.position == self.item.position
+            && current_state
+                .villager
+                .can_carry(&current_state.catalogue, &self.item.id)
+            && current_state.catalogue.is_pickable(&self.item.id)
+            && current_state.task_on(&self.item.id).is_none()
+            && current_state.is_available(&self.item)
     }
 }
 
+impl Executable<VillageState> for PickUpItem {}
+
+// Carries out one of the tasks on the item the villager is stood on.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub(crate) struct ChopTree {
+pub(crate) struct Work {
+    task: Arc<Task>,
     item: Item,
 }
 
-impl Action<VillageState> for ChopTree {
-    fn act(&self, current_state: VillageState) -> VillageState {
-        let mut new_state = current_state.clone();
+impl Work {
+    // The target as it is now, if there's anything left of it.
+    fn target<'a>(&self, current_state: &'a VillageState) -> Option<&'a Item> {
+        current_state
+            .items
+            .iter()
+            .find(|item| item.is(&self.item) && item.amount > 0)
+    }
 
-        let wood = Item {
-            position: self.item.position,
-            id: "wood".into(),
+    // Brings what's left of the target down to `left` of what there was, saying whether that's the last of it.
+    // Resources that don't grow back go once they're used up.
+    fn deplete(current_state: &mut VillageState, target: &Item, left: impl Fn(u32) -> u32) -> bool {
+        let Some(i) = current_state.items.iter().position(|item| item.is(target)) else {
+            return false;
         };
-        new_state.items.push(wood);
+        let items = Arc::make_mut(&mut current_state.items);
+        let item = &mut items[i];
+        item.amount = left(item.amount);
+        let spent = item.amount == 0;
+        if spent && current_state.catalogue.refill(&target.id).is_none() {
+            items.remove(i);
+        }
+        spent
+    }
 
-        if let Some(i) = new_state.items.iter().position(|item| item == &self.item) {
-            new_state.items.remove(i);
+    // One go at the target, leaving behind `drops` (one count for each of the task's) and giving the villager
+    // whatever it has room for, along with each of the byproducts that `comes`.
+    fn finish(
+        &self,
+        current_state: VillageState,
+        drops: &[usize],
+        mut comes: impl FnMut(&Byproduct) -> bool,
+    ) -> VillageState {
+        let mut new_state = current_state.clone();
+
+        for ((id, _, _), &count) in self.task.drops().iter().zip(drops) {
+            for _ in 0..count {
+                Arc::make_mut(&mut new_state.items).push(Item::new(id.clone(), self.item.position));
+            }
+        }
+
+        let inventory = &mut new_state.villager.inventory;
+        for used in &self.task.consumes {
+            if let Some(i) = inventory.iter().position(|id| id == used) {
+                inventory.remove(i);
+            }
+        }
+        for tool in self.task.tools_used(&current_state.villager.inventory) {
+            new_state.villager.use_tool(&current_state.catalogue, tool);
+        }
+        let spent = Self::deplete(&mut new_state, &self.item, |amount| {
+            amount.saturating_sub(1)
+        });
+        let byproducts = self
+            .task
+            .byproducts
+            .iter()
+            .filter(|byproduct| byproduct.applies(&current_state.villager.inventory, spent))
+            .filter(|byproduct| comes(byproduct))
+            .map(|byproduct| &byproduct.item);
+        for given in self.task.gives.iter().chain(byproducts) {
+            if new_state.villager.can_carry(&new_state.catalogue, given) {
+                stash(&mut new_state.villager.inventory, given.clone());
+            }
         }
 
         new_state
     }
+}
+
+impl Action<VillageState> for Work {
+    fn act(&self, current_state: VillageState) -> VillageState {
+        let drops: Vec<_> = self
+            .task
+            .drops()
+            .iter()
+            .map(|&(_, least, most)| expected(least, most))
+            .collect();
+        // Only what's certain to come is counted on.
+        self.finish(current_state, &drops, |byproduct| byproduct.is_certain())
+    }
+
+    fn duration(&self, current_state: &VillageState) -> u64 {
+        self.task.duration_with(&current_state.villager.inventory)
+    }
 
-    fn cost(&self) -> u64 {
-        1
+    fn cost(&self, current_state: &VillageState) -> u64 {
+        expected_ticks(self.duration(current_state), self.task.chance())
     }
 
     fn prerequisite(&self, current_state: &VillageState) -> bool {
+        let inventory = &current_state.villager.inventory;
         current_state.villager.position == self.item.position
+            && self.item.id == self.task.target
+            && carries(inventory, &self.task.requires)
+            && carries(inventory, &self.task.consumes)
+            && self.target(current_state).is_some()
+            && current_state.is_available(&self.item)
+    }
+}
+
+impl Executable<VillageState> for Work {
+    fn outcome(&self, current_state: &mut VillageState, rng: &mut dyn RngCore) -> Status {
+        let (chance, out_of) = self.task.chance();
+        if !rng.gen_ratio(chance, out_of) {
+            // Nothing to show for it, though it takes one of the target all the same.
+            Self::deplete(current_state, &self.item, |left| left.saturating_sub(1));
+            return Status::Failed;
+        }
+
+        let drops: Vec<_> = self
+            .task
+            .drops()
+            .iter()
+            .map(|&(_, least, most)| rng.gen_range(least..=most))
+            .collect();
+        *current_state = self.finish(current_state.clone(), &drops, |byproduct| {
+            let (chance, out_of) = byproduct.chance();
+            rng.gen_ratio(chance, out_of)
+        });
+        Status::Succeeded
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct Craft {
+    recipe: Recipe,
+}
+
+impl Action<VillageState> for Craft {
+    fn act(&self, current_state: VillageState) -> VillageState {
+        let mut new_state = current_state.clone();
+        let inventory = &mut new_state.villager.inventory;
+
+        for input in &self.recipe.inputs {
+            if let Some(i) = inventory.iter().position(|id| id == input) {
+                inventory.remove(i);
+            }
+        }
+        for output in &self.recipe.outputs {
+            stash(inventory, output.clone());
+        }
+
+        new_state
+    }
+
+    fn duration(&self, _current_state: &VillageState) -> u64 {
+        self.recipe.duration
+    }
+
+    fn prerequisite(&self, current_state: &VillageState) -> bool {
+        let at_station = self.recipe.station.as_ref().is_none_or(|station| {
+            current_state
+                .items
+                .iter()
+                .any(|item| &item.id == station && item.position == current_state.villager.position)
+        });
+
+        // The outputs have to be carried away too, though the inputs go into them.
+        let catalogue = &current_state.catalogue;
+        let weighs = |ids: &[String]| ids.iter().map(|id| catalogue.weight(id)).sum::<u32>();
+        let villager = &current_state.villager;
+        let fits = villager.load(catalogue) + weighs(&self.recipe.outputs)
+            <= villager.capacity + weighs(&self.recipe.inputs);
+
+        at_station && fits && carries(&villager.inventory, &self.recipe.inputs)
+    }
+}
+
+impl Executable<VillageState> for Craft {}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct Eat {
+    food: String,
+    nourishment: u8,
+}
+
+impl Eat {
+    // Something to eat from the villager's inventory, as long as it's hungry.
+    pub(crate) fn from_inventory(current_state: &VillageState) -> Option<Self> {
+        current_state
+            .catalogue
+            .foods()
+            .into_iter()
+            .find_map(|(food, nourishment)| {
+                let action = Eat {
+                    food: food.to_string(),
+                    nourishment,
+                };
+                action.prerequisite(current_state).then_some(action)
+            })
+    }
+}
+
+impl Action<VillageState> for Eat {
+    fn act(&self, current_state: VillageState) -> VillageState {
+        let mut new_state = current_state.clone();
+
+        if let Some(i) = new_state
+            .villager
+            .inventory
+            .iter()
+            .position(|id| id == &self.food)
+        {
+            new_state.villager.inventory.remove(i);
+            new_state.villager.eat(self.nourishment);
+        }
+
+        new_state
+    }
+
+    fn prerequisite(&self, current_state: &VillageState) -> bool {
+        current_state.villager.satiety < HUNGRY
+            && current_state.villager.inventory.contains(&self.food)
+    }
+}
+
+impl Executable<VillageState> for Eat {}
+
+// One of each kind of item in `ids`.
+fn kinds(ids: &[String]) -> Vec<String> {
+    let mut kinds = ids.to_vec();
+    kinds.sort_unstable();
+    kinds.dedup();
+    kinds
+}
+
+// Puts an item down where the villager stands, for someone (maybe the villager itself) to pick up later.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct DropItem {
+    item: String,
+}
+
+impl DropItem {
+    // Only worth planning for to make room in a full inventory, otherwise it would just be clutter.
+    fn available(current_state: &VillageState) -> Vec<Self> {
+        if !current_state.villager.is_full(&current_state.catalogue) {
+            return vec![];
+        }
+        kinds(&current_state.villager.inventory)
+            .into_iter()
+            .map(|item| Self { item })
+            .filter(|action| action.prerequisite(current_state))
+            .collect()
+    }
+}
+
+impl Action<VillageState> for DropItem {
+    fn act(&self, current_state: VillageState) -> VillageState {
+        let mut new_state = current_state.clone();
+
+        let villager = &mut new_state.villager;
+        if let Some(uses) = unstash(&mut villager.inventory, &mut villager.wear, &self.item) {
+            let dropped = Item::new(self.item.clone(), villager.position).with_wear(uses);
+            Arc::make_mut(&mut new_state.items).push(dropped);
+        }
+
+        new_state
+    }
+
+    // Nothing that would take root or stay put on its own; saplings get planted instead.
+    fn prerequisite(&self, current_state: &VillageState) -> bool {
+        current_state.villager.inventory.contains(&self.item)
+            && current_state.catalogue.is_pickable(&self.item)
+    }
+}
+
+impl Executable<VillageState> for DropItem {}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct Deposit {
+    item: String,
+}
+
+impl Deposit {
+    fn available(current_state: &VillageState) -> Vec<Self> {
+        kinds(&current_state.villager.inventory)
+            .into_iter()
+            .map(|item| Self { item })
+            .filter(|action| action.prerequisite(current_state))
+            .collect()
+    }
+}
+
+impl Action<VillageState> for Deposit {
+    fn act(&self, current_state: VillageState) -> VillageState {
+        let mut new_state = current_state.clone();
+
+        let (villager, stockpile) = (&mut new_state.villager, &mut new_state.stockpile);
+        if let Some(uses) = unstash(&mut villager.inventory, &mut villager.wear, &self.item) {
+            stash_worn(
+                &mut stockpile.contents,
+                &mut stockpile.wear,
+                self.item.clone(),
+                uses,
+            );
+        }
+
+        new_state
+    }
+
+    fn prerequisite(&self, current_state: &VillageState) -> bool {
+        current_state
+            .stockpile
+            .contains(current_state.villager.position)
+            && current_state.villager.inventory.contains(&self.item)
+    }
+}
+
+impl Executable<VillageState> for Deposit {}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct Withdraw {
+    item: String,
+}
+
+impl Withdraw {
+    fn available(current_state: &VillageState) -> Vec<Self> {
+        kinds(&current_state.stockpile.contents)
+            .into_iter()
+            .map(|item| Self { item })
+            .filter(|action| action.prerequisite(current_state))
+            .collect()
+    }
+}
+
+impl Action<VillageState> for Withdraw {
+    fn act(&self, current_state: VillageState) -> VillageState {
+        let mut new_state = current_state.clone();
+
+        let (villager, stockpile) = (&mut new_state.villager, &mut new_state.stockpile);
+        if let Some(uses) = unstash(&mut stockpile.contents, &mut stockpile.wear, &self.item) {
+            stash_worn(
+                &mut villager.inventory,
+                &mut villager.wear,
+                self.item.clone(),
+                uses,
+            );
+        }
+
+        new_state
+    }
+
+    fn prerequisite(&self, current_state: &VillageState) -> bool {
+        current_state
+            .stockpile
+            .contains(current_state.villager.position)
+            && current_state
+                .villager
+                .can_carry(&current_state.catalogue, &self.item)
+            && current_state.stockpile.contents.contains(&self.item)
+    }
+}
+
+impl Executable<VillageState> for Withdraw {}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct Construct {
+    site: Building,
+}
+
+impl Construct {
+    fn materials<'a>(&self, current_state: &'a VillageState) -> Option<&'a [String]> {
+        current_state
+            .blueprint(&self.site.blueprint)
+            .map(|blueprint| blueprint.materials.as_slice())
+    }
+
+    fn has_materials(&self, current_state: &VillageState) -> bool {
+        self.materials(current_state)
+            .is_some_and(|materials| carries(&current_state.villager.inventory, materials))
+    }
+}
+
+impl Action<VillageState> for Construct {
+    fn act(&self, current_state: VillageState) -> VillageState {
+        let mut new_state = current_state.clone();
+
+        let materials = self.materials(&current_state).unwrap_or_default();
+        let inventory = &mut new_state.villager.inventory;
+        for material in materials {
+            if let Some(i) = inventory.iter().position(|id| id == material) {
+                inventory.remove(i);
+            }
+        }
+
+        if let Some(i) = new_state.sites.iter().position(|site| site == &self.site) {
+            let building = Arc::make_mut(&mut new_state.sites).remove(i);
+            Arc::make_mut(&mut new_state.buildings).push(building);
+        }
+
+        new_state
+    }
+
+    fn duration(&self, current_state: &VillageState) -> u64 {
+        current_state
+            .blueprint(&self.site.blueprint)
+            .map_or(1, |blueprint| blueprint.duration)
+    }
+
+    fn prerequisite(&self, current_state: &VillageState) -> bool {
+        current_state.villager.position == self.site.corner
+            && current_state.sites.contains(&self.site)
+            && self.has_materials(current_state)
+    }
+}
+
+impl Executable<VillageState> for Construct {}
+
+// Seedlings take a moment to bed in.
+const PLANTING_DURATION: u64 = 3;
+
+// Puts a seedling (a sapling, say) in the ground to grow.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct Plant {
+    item: String,
+}
+
+impl Plant {
+    // Planting whatever the villager has to plant where it stands, or else heading for the nearest spot it could.
+    fn available(current_state: &VillageState) -> Vec<VillagerActionEnum> {
+        let plants: Vec<_> = kinds(&current_state.villager.inventory)
+            .into_iter()
+            .filter(|id| current_state.catalogue.is_seedling(id))
+            .map(|item| Self { item })
+            .collect();
+        if plants.is_empty() {
+            return vec![];
+        }
+
+        let taken = taken(current_state);
+        if is_clear(current_state, &taken, current_state.villager.position) {
+            return plants.into_iter().map(VillagerActionEnum::Plant).collect();
+        }
+        let is_clear = |position| is_clear(current_state, &taken, position);
+        route_to_nearest(
+            &current_state.world,
+            current_state.villager.position,
+            &is_clear,
+            PLANTING_REACH,
+        )
+        .map(|route| VillagerActionEnum::Move(Move { route }))
+        .into_iter()
+        .collect()
+    }
+}
+
+// How far a villager will go to find somewhere to plant, in ticks.
+const PLANTING_REACH: u64 = 40;
+
+// Everywhere something's lying, which has no room for planting.
+fn taken(current_state: &VillageState) -> HashSet<(i64, i64)> {
+    current_state
+        .items
+        .iter()
+        .map(|item| item.position)
+        .collect()
+}
+
+// Whether `position` is clear for planting: not `taken`, and out of the way of the village.
+fn is_clear(
+    current_state: &VillageState,
+    taken: &HashSet<(i64, i64)>,
+    position: (i64, i64),
+) -> bool {
+    let in_village = current_state.stockpile.contains(position)
+        || current_state
+            .sites
+            .iter()
+            .chain(current_state.buildings.iter())
+            .any(|building| {
+                current_state
+                    .blueprint(&building.blueprint)
+                    .is_some_and(|blueprint| blueprint.covers(building, position))
+            });
+    !in_village && !taken.contains(&position)
+}
+
+impl Action<VillageState> for Plant {
+    fn act(&self, current_state: VillageState) -> VillageState {
+        let mut new_state = current_state.clone();
+
+        let inventory = &mut new_state.villager.inventory;
+        if let Some(i) = inventory.iter().position(|id| id == &self.item) {
+            inventory.remove(i);
+            Arc::make_mut(&mut new_state.items).push(Item::new(
+                self.item.clone(),
+                current_state.villager.position,
+            ));
+        }
+
+        new_state
+    }
+
+    fn duration(&self, _current_state: &VillageState) -> u64 {
+        PLANTING_DURATION
+    }
+
+    fn prerequisite(&self, current_state: &VillageState) -> bool {
+        current_state.villager.inventory.contains(&self.item)
+            && is_clear(
+                current_state,
+                &taken(current_state),
+                current_state.villager.position,
+            )
+    }
+}
+
+impl Executable<VillageState> for Plant {}
+
+// Whether `other` is close enough to hand things to.
+fn near(current_state: &VillageState, other: &Villager) -> bool {
+    distance(current_state.villager.position, other.position) <= 1
+}
+
+// Hands one `item` over to villager `to`, which only happens when they're in need of it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct Give {
+    item: String,
+    to: VillagerId,
+}
+
+impl Give {
+    fn candidates(current_state: &VillageState, other: &Villager) -> Vec<Self> {
+        kinds(&current_state.villager.inventory)
+            .into_iter()
+            .filter(|item| other.needs(&current_state.catalogue, item))
+            .map(|item| Self { item, to: other.id })
+            .collect()
+    }
+}
+
+impl Action<VillageState> for Give {
+    fn act(&self, current_state: VillageState) -> VillageState {
+        let mut new_state = current_state.clone();
+
+        let villager = &mut new_state.villager;
+        if let Some(uses) = unstash(&mut villager.inventory, &mut villager.wear, &self.item) {
+            if let Some(other) = new_state.other_mut(self.to) {
+                stash_worn(
+                    &mut other.inventory,
+                    &mut other.wear,
+                    self.item.clone(),
+                    uses,
+                );
+            }
+        }
+
+        new_state
+    }
+
+    fn prerequisite(&self, current_state: &VillageState) -> bool {
+        current_state.villager.inventory.contains(&self.item)
+            && current_state.other(self.to).is_some_and(|other| {
+                near(current_state, other) && other.needs(&current_state.catalogue, &self.item)
+            })
+    }
+}
+
+impl Executable<VillageState> for Give {}
+
+// Swaps one `give` for one `get` with villager `with`. Both sides only part with something they can spare, and only
+// for something they don't have yet.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct Trade {
+    give: String,
+    get: String,
+    with: VillagerId,
+}
+
+// Haggling takes a little while.
+const TRADING_DURATION: u64 = 2;
+
+impl Trade {
+    fn candidates(current_state: &VillageState, other: &Villager) -> Vec<Self> {
+        let villager = &current_state.villager;
+        let wanted: Vec<_> = kinds(&other.inventory)
+            .into_iter()
+            .filter(|get| other.can_spare(get) && villager.count(get) == 0)
+            .collect();
+
+        kinds(&villager.inventory)
+            .into_iter()
+            .filter(|give| villager.can_spare(give) && other.count(give) == 0)
+            .flat_map(|give| {
+                wanted.iter().map(move |get| Self {
+                    give: give.clone(),
+                    get: get.clone(),
+                    with: other.id,
+                })
+            })
+            .collect()
+    }
+
+    fn agreed(&self, current_state: &VillageState, other: &Villager) -> bool {
+        let (villager, catalogue) = (&current_state.villager, &current_state.catalogue);
+        let (give, get) = (catalogue.weight(&self.give), catalogue.weight(&self.get));
+        other.is_alive()
+            && villager.load(catalogue) + get <= villager.capacity + give
+            && other.load(catalogue) + give <= other.capacity + get
+            && villager.can_spare(&self.give)
+            && villager.count(&self.get) == 0
+            && other.can_spare(&self.get)
+            && other.count(&self.give) == 0
+    }
+}
+
+impl Action<VillageState> for Trade {
+    fn act(&self, current_state: VillageState) -> VillageState {
+        let mut new_state = current_state.clone();
+
+        let villager = &mut new_state.villager;
+        let Some(given) = unstash(&mut villager.inventory, &mut villager.wear, &self.give) else {
+            return current_state;
+        };
+        let Some(other) = new_state.other_mut(self.with) else {
+            return current_state;
+        };
+        let Some(got) = unstash(&mut other.inventory, &mut other.wear, &self.get) else {
+            return current_state;
+        };
+        stash_worn(
+            &mut other.inventory,
+            &mut other.wear,
+            self.give.clone(),
+            given,
+        );
+
+        let villager = &mut new_state.villager;
+        stash_worn(
+            &mut villager.inventory,
+            &mut villager.wear,
+            self.get.clone(),
+            got,
+        );
+
+        new_state
+    }
+
+    fn duration(&self, _current_state: &VillageState) -> u64 {
+        TRADING_DURATION
+    }
+
+    fn prerequisite(&self, current_state: &VillageState) -> bool {
+        current_state
+            .other(self.with)
+            .is_some_and(|other| near(current_state, other) && self.agreed(current_state, other))
+    }
+}
+
+impl Executable<VillageState> for Trade {}
+
+// Takes a swing at wolf `wolf`, which has to be right next to the villager. Anything to hand that does damage makes a
+// better weapon than bare hands, though it wears like it would with any other use.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct Attack {
+    wolf: WolfId,
+}
+
+// Ticks each swing takes.
+const ATTACK_DURATION: u64 = 2;
+// What a swing does to a wolf with no weapon to hand.
+const BARE_HANDS: u8 = 5;
+
+impl Attack {
+    // The best weapon the villager has on it, if any, and what a swing with it does.
+    fn weapon(catalogue: &Catalogue, villager: &Villager) -> (Option<String>, u8) {
+        villager
+            .inventory
+            .iter()
+            .filter_map(|id| Some((id, catalogue.damage(id)?)))
+            .max_by_key(|&(_, damage)| damage)
+            .map_or((None, BARE_HANDS), |(weapon, damage)| {
+                (Some(weapon.clone()), damage)
+            })
+    }
+}
+
+impl Action<VillageState> for Attack {
+    fn act(&self, current_state: VillageState) -> VillageState {
+        let mut new_state = current_state.clone();
+
+        let catalogue = &current_state.catalogue;
+        let (weapon, damage) = Self::weapon(catalogue, &new_state.villager);
+        if let Some(weapon) = weapon {
+            new_state.villager.use_tool(catalogue, &weapon);
+        }
+        let wolves = Arc::make_mut(&mut new_state.wolves);
+        if let Some(wolf) = wolves.iter_mut().find(|wolf| wolf.id == self.wolf) {
+            wolf.health.sap(damage);
+        }
+        wolves.retain(Wolf::is_alive);
+
+        new_state
+    }
+
+    fn duration(&self, _current_state: &VillageState) -> u64 {
+        ATTACK_DURATION
+    }
+
+    // Fighting is a worse idea the more hurt the villager already is.
+    fn cost(&self, current_state: &VillageState) -> u64 {
+        let health = current_state.villager.health.check().unwrap_or(0).max(1) as u64;
+        ATTACK_DURATION * 100 / health
+    }
+
+    fn prerequisite(&self, current_state: &VillageState) -> bool {
+        let position = current_state.villager.position;
+        current_state.villager.is_alive()
+            && current_state
+                .wolves
+                .iter()
+                .any(|wolf| wolf.id == self.wolf && distance(position, wolf.position) <= 1)
+    }
+}
+
+impl Executable<VillageState> for Attack {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::goals::{HaveTool, PlantTrees};
+    use crate::simulation::{Simulation, SimulationConfig};
+    use outbound_goap::{plan_for, Goal};
+
+    // A freshly settled village, with the villager stood a few steps into the stockpile and no wolves about.
+    fn village() -> VillageState {
+        let mut state = Simulation::new(SimulationConfig::default()).into_state();
+        let (left, top) = state.stockpile.corner;
+        state.villager.position = (left + 2, top + 2);
+        state.wolves = Arc::new(vec![]);
+        state
+    }
+
+    fn plan(
+        state: &VillageState,
+        goal: &dyn Goal<VillageState>,
+    ) -> Option<Vec<VillagerActionEnum>> {
+        plan_for(&state.perceived(), goal)
+    }
+
+    #[test]
+    fn an_axe_is_planned_from_stock() {
+        let mut state = village();
+        state.stockpile.contents =
+            [vec!["stone".to_string(); 6], vec!["wood".to_string(); 6]].concat();
+
+        let plan = plan(&state, &HaveTool { tool: "axe" }).expect("a plan for an axe");
+        assert!(matches!(plan.last(), Some(VillagerActionEnum::Craft(_))));
+    }
+
+    #[test]
+    fn saplings_are_taken_out_of_the_village_to_be_planted() {
+        let mut state = village();
+        state.villager.inventory = vec!["sapling".to_string(); 3];
+
+        let plan = plan(&state, &PlantTrees {}).expect("a plan for planting");
+        assert!(matches!(
+            plan.as_slice(),
+            [VillagerActionEnum::Move(_), VillagerActionEnum::Plant(_)]
+        ));
+    }
+
+    #[test]
+    fn saplings_are_planted_beside_whatever_stands_in_the_way() {
+        let mut state = village();
+        let (left, top) = state.stockpile.corner;
+        state.villager.position = (left - 5, top);
+        let (world, position) = (state.world, state.villager.position);
+        state.villager.map.look(&world, position);
+        state.villager.inventory = vec!["sapling".to_string()];
+        Arc::make_mut(&mut state.items)
+            .push(Item::new("rock".to_string(), state.villager.position));
+
+        let plan = plan(&state, &PlantTrees {}).expect("a plan for planting");
+        let [VillagerActionEnum::Move(walk), VillagerActionEnum::Plant(_)] = plan.as_slice() else {
+            panic!("expected a short walk and planting, not {plan:?}");
+        };
+        assert_eq!(walk.route.cost, 1);
+    }
+
+    #[test]
+    fn saplings_put_by_are_fetched_for_planting() {
+        let mut state = village();
+        state.stockpile.contents = vec!["sapling".to_string()];
+
+        let plan = plan(&state, &PlantTrees {}).expect("a plan for planting");
+        assert!(matches!(
+            plan.first(),
+            Some(VillagerActionEnum::Withdraw(_))
+        ));
+        assert!(matches!(plan.last(), Some(VillagerActionEnum::Plant(_))));
     }
 }
@@ -0,0 +1,104 @@
+use std::fmt;
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::Path;
+
+use serde::de::DeserializeOwned;
+
+// Where the game looks for its data files, relative to wherever it's run from.
+const ASSETS: &str = "assets";
+
+// The copies of the data files built into the game.
+pub(crate) const ITEMS: &str = include_str!("../assets/items.ron");
+pub(crate) const RECIPES: &str = include_str!("../assets/recipes.ron");
+pub(crate) const STRUCTURES: &str = include_str!("../assets/structures.ron");
+pub(crate) const TASKS: &str = include_str!("../assets/tasks.ron");
+
+/// Why one of the data files in `assets/` couldn't be loaded.
+#[derive(Debug)]
+pub enum AssetError {
+    Read {
+        name: String,
+        error: io::Error,
+    },
+    Parse {
+        name: String,
+        error: ron::error::SpannedError,
+    },
+}
+
+impl fmt::Display for AssetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AssetError::Read { name, error } => write!(f, "couldn't read {ASSETS}/{name}: {error}"),
+            AssetError::Parse { name, error } => write!(f, "{ASSETS}/{name} isn't valid: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for AssetError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AssetError::Read { error, .. } => Some(error),
+            AssetError::Parse { error, .. } => Some(error),
+        }
+    }
+}
+
+// The data file `name`, read from disk so it can be tweaked without a rebuild. The copy built into the game
+// (`embedded`) is only used when there's no such file, so the game still runs from anywhere.
+pub(crate) fn load<T: DeserializeOwned>(
+    name: &str,
+    embedded: &'static str,
+) -> Result<T, AssetError> {
+    let text = match fs::read_to_string(Path::new(ASSETS).join(name)) {
+        Ok(text) => text,
+        Err(error) if error.kind() == ErrorKind::NotFound => embedded.to_string(),
+        Err(error) => {
+            return Err(AssetError::Read {
+                name: name.to_string(),
+                error,
+            })
+        }
+    };
+    ron::from_str(&text).map_err(|error| AssetError::Parse {
+        name: name.to_string(),
+        error,
+    })
+}
+
+// One of the copies built into the game, which are checked by the tests and so can't fail to parse.
+pub(crate) fn shipped<T: DeserializeOwned>(embedded: &'static str) -> T {
+    ron::from_str(embedded).unwrap_or_else(|error| panic!("a shipped asset is invalid: {error}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::item::ItemKind;
+    use crate::recipe::Recipe;
+    use crate::structure::Blueprint;
+    use crate::task::Task;
+
+    #[test]
+    fn the_shipped_assets_are_valid() {
+        assert!(ron::from_str::<Vec<ItemKind>>(ITEMS).is_ok());
+        assert!(ron::from_str::<Vec<Recipe>>(RECIPES).is_ok());
+        assert!(ron::from_str::<Vec<Blueprint>>(STRUCTURES).is_ok());
+        assert!(ron::from_str::<Vec<Task>>(TASKS).is_ok());
+    }
+
+    #[test]
+    fn a_missing_file_falls_back_on_the_shipped_copy() {
+        let tasks: Vec<Task> = load("no such file.ron", TASKS).unwrap();
+        assert_eq!(tasks, Task::defaults());
+    }
+
+    #[test]
+    fn a_broken_file_is_reported_rather_than_panicking() {
+        let error = load::<Vec<Task>>("no such file.ron", "[(name: ").unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("assets/no such file.ron isn't valid"));
+    }
+}
@@ -0,0 +1,454 @@
+// A small behaviour tree sitting between goal selection and plan execution. Reactive behaviours (the kind that must
+// interrupt whatever the villager is doing) are cheap condition/action nodes, the long-winded stuff is left to GOAP
+// through a `Plan` leaf.
+//
+// Composite nodes are re-evaluated from their first child every tick, so a higher priority branch can take over from
+// one that is still running. The interrupted branch is reset, which cancels any plan it was carrying out.
+//
+// Planning is paid for out of a budget for each tick, of either time or states to expand, and a search that runs out
+// of budget carries on the next tick, so no one tick ever takes too long.
+use crate::actions::{VillageState, VillagerActionEnum};
+use crate::reservation::CLAIM_TTL;
+use crate::simulation::PlanningBudget;
+use std::collections::VecDeque;
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, OnceLock};
+use std::thread;
+use std::time::Instant;
+
+use outbound_goap::{by_priority, Executor, Goal, Planning, Search, Status};
+use rand::RngCore;
+
+pub(crate) enum Behaviour {
+    // Ticks children in order for as long as they succeed.
+    Sequence(Composite),
+    // Ticks children in order until one of them doesn't fail.
+    Selector(Composite),
+    // Rewrites the status of its child.
+    Decorator(fn(Status) -> Status, Box<Behaviour>),
+    Condition(fn(&VillageState) -> bool),
+    Action(fn(&mut VillageState) -> Status),
+    Plan(PlanLeaf),
+}
+
+impl Behaviour {
+    pub(crate) fn sequence(children: Vec<Behaviour>) -> Self {
+        Self::Sequence(Composite::new(children))
+    }
+
+    pub(crate) fn selector(children: Vec<Behaviour>) -> Self {
+        Self::Selector(Composite::new(children))
+    }
+
+    pub(crate) fn invert(child: Behaviour) -> Self {
+        Self::Decorator(invert, Box::new(child))
+    }
+
+    pub(crate) fn plan(goals: Vec<Box<dyn Goal<VillageState>>>) -> Self {
+        Self::Plan(PlanLeaf::new(goals))
+    }
+
+    // Ticks the node, spending whatever planning it does out of `budget`.
+    pub(crate) fn tick(
+        &mut self,
+        current_state: &mut VillageState,
+        now: u64,
+        rng: &mut dyn RngCore,
+        budget: &mut Budget,
+    ) -> Status {
+        match self {
+            Behaviour::Sequence(c) => c.tick(current_state, now, rng, budget, Status::Succeeded),
+            Behaviour::Selector(c) => c.tick(current_state, now, rng, budget, Status::Failed),
+            Behaviour::Decorator(decorate, child) => {
+                decorate(child.tick(current_state, now, rng, budget))
+            }
+            Behaviour::Condition(condition) => {
+                if condition(current_state) {
+                    Status::Succeeded
+                } else {
+                    Status::Failed
+                }
+            }
+            Behaviour::Action(action) => action(current_state),
+            Behaviour::Plan(p) => p.tick(current_state, now, rng, budget),
+        }
+    }
+
+    // The action the plan being carried out is part way through, if any, and the ticks spent on it so far.
+    pub(crate) fn progress(&self) -> Option<(&VillagerActionEnum, u64)> {
+        match self {
+            Behaviour::Sequence(c) | Behaviour::Selector(c) => c.children[c.running?].progress(),
+            Behaviour::Decorator(_, child) => child.progress(),
+            Behaviour::Condition(_) | Behaviour::Action(_) => None,
+            Behaviour::Plan(p) => p.executor.as_ref()?.progress(),
+        }
+    }
+
+    // Abandon whatever this node was in the middle of.
+    pub(crate) fn reset(&mut self, current_state: &mut VillageState) {
+        match self {
+            Behaviour::Sequence(c) | Behaviour::Selector(c) => c.reset(current_state),
+            Behaviour::Decorator(_, child) => child.reset(current_state),
+            Behaviour::Condition(_) | Behaviour::Action(_) => {}
+            Behaviour::Plan(p) => p.reset(current_state),
+        }
+    }
+}
+
+fn invert(status: Status) -> Status {
+    match status {
+        Status::Running => Status::Running,
+        Status::Succeeded => Status::Failed,
+        Status::Failed => Status::Succeeded,
+    }
+}
+
+pub(crate) struct Composite {
+    children: Vec<Behaviour>,
+    running: Option<usize>,
+}
+
+impl Composite {
+    fn new(children: Vec<Behaviour>) -> Self {
+        Self {
+            children,
+            running: None,
+        }
+    }
+
+    // Tick children in order while they return `carry_on`, stopping at the first that doesn't.
+    fn tick(
+        &mut self,
+        current_state: &mut VillageState,
+        now: u64,
+        rng: &mut dyn RngCore,
+        budget: &mut Budget,
+        carry_on: Status,
+    ) -> Status {
+        for i in 0..self.children.len() {
+            let status = self.children[i].tick(current_state, now, rng, budget);
+            if status != carry_on {
+                self.set_running((status == Status::Running).then_some(i), current_state);
+                return status;
+            }
+        }
+
+        self.set_running(None, current_state);
+        carry_on
+    }
+
+    fn set_running(&mut self, running: Option<usize>, current_state: &mut VillageState) {
+        if let Some(previous) = self.running {
+            if Some(previous) != running {
+                self.children[previous].reset(current_state);
+            }
+        }
+        self.running = running;
+    }
+
+    fn reset(&mut self, current_state: &mut VillageState) {
+        self.set_running(None, current_state);
+    }
+}
+
+// What's left of a tick's planning budget.
+pub(crate) enum Budget {
+    Expansions(usize),
+    Until(Instant),
+}
+
+impl Budget {
+    pub(crate) fn new(planning: PlanningBudget) -> Self {
+        match planning {
+            PlanningBudget::Expansions(expansions) => Budget::Expansions(expansions),
+            PlanningBudget::Time(time) => Budget::Until(Instant::now() + time),
+        }
+    }
+
+    // An even share of what's left between `among` villagers, taken out of this budget until it's handed back.
+    pub(crate) fn share(&mut self, among: usize) -> Self {
+        match self {
+            Budget::Expansions(left) => {
+                let share = *left / among;
+                *left -= share;
+                Budget::Expansions(share)
+            }
+            Budget::Until(end) => {
+                let now = Instant::now();
+                Budget::Until(now + end.saturating_duration_since(now) / among as u32)
+            }
+        }
+    }
+
+    // Hand back whatever of a share went unspent, for those still to take their turn. Time left over hands itself back.
+    pub(crate) fn hand_back(&mut self, share: Self) {
+        if let (Budget::Expansions(left), Budget::Expansions(unspent)) = (self, share) {
+            *left += unspent;
+        }
+    }
+
+    fn search(
+        &mut self,
+        planning: &mut Planning<VillageState, VillagerActionEnum>,
+    ) -> Search<VillagerActionEnum> {
+        match self {
+            Budget::Expansions(left) => planning.search(left),
+            // A state at a time, checking the clock in between.
+            Budget::Until(end) => loop {
+                let out_of_time = Instant::now() >= *end;
+                match planning.search(&mut usize::from(!out_of_time)) {
+                    Search::Pending if !out_of_time => {}
+                    done => return done,
+                }
+            },
+        }
+    }
+}
+
+// Ticks a goal is left alone for after failing to find a plan for it, rather than trying (and failing, slowly) again
+// straight away.
+const GIVE_UP_TICKS: u64 = 100;
+
+// A search that's been at it a while holds thousands of states, and freeing them all is enough to stall a tick, so
+// they're handed to a thread that does nothing else but free them.
+fn discard<T: Send + 'static>(garbage: Option<T>) {
+    static BIN: OnceLock<Sender<Box<dyn Send>>> = OnceLock::new();
+    let Some(garbage) = garbage else {
+        return;
+    };
+    let bin = BIN.get_or_init(|| {
+        let (bin, emptied) = mpsc::channel::<Box<dyn Send>>();
+        thread::spawn(move || emptied.into_iter().for_each(drop));
+        bin
+    });
+    // Should that thread ever have gone, the garbage comes back and is freed here instead.
+    let _ = bin.send(Box::new(garbage));
+}
+
+// Plans towards the most pressing of its goals and carries the plan out. Running while it's still thinking, it succeeds
+// when the plan does and fails if no plan can be found or the plan falls apart part way through.
+pub(crate) struct PlanLeaf {
+    goals: Vec<Box<dyn Goal<VillageState>>>,
+    // The tick each goal can next be planned for.
+    retry_at: Vec<u64>,
+    thinking: Option<Box<Thinking>>,
+    executor: Option<Executor<VillageState, VillagerActionEnum>>,
+}
+
+// A search for a plan that's under way.
+struct Thinking {
+    // The village as the villager saw it when it started thinking.
+    perceived: VillageState,
+    // Goals still to try, most pressing first, and the search for the first of them once it's begun.
+    goals: VecDeque<usize>,
+    planning: Option<Planning<VillageState, VillagerActionEnum>>,
+}
+
+impl PlanLeaf {
+    fn new(goals: Vec<Box<dyn Goal<VillageState>>>) -> Self {
+        Self {
+            retry_at: vec![0; goals.len()],
+            goals,
+            thinking: None,
+            executor: None,
+        }
+    }
+
+    fn tick(
+        &mut self,
+        current_state: &mut VillageState,
+        now: u64,
+        rng: &mut dyn RngCore,
+        budget: &mut Budget,
+    ) -> Status {
+        let executor = match &mut self.executor {
+            Some(executor) => executor,
+            None => match self.think(current_state, now, budget) {
+                Search::Found(new_plan) => self.executor.insert(Executor::new(new_plan)),
+                Search::Pending => return Status::Running,
+                Search::Unreachable => return Status::Failed,
+            },
+        };
+
+        // A plan that falls apart (an action failing, or not coming off) is dropped, and a fresh one made next tick.
+        let status = executor.tick(current_state, rng);
+        match status {
+            Status::Running => {}
+            Status::Succeeded => self.executor = None,
+            Status::Failed => self.reset(current_state),
+        }
+        status
+    }
+
+    // Carries on looking for a plan, starting afresh if there's no search under way. The villager's old claims are
+    // dropped on starting, and whatever the new plan needs claimed once there is one.
+    fn think(
+        &mut self,
+        current_state: &mut VillageState,
+        now: u64,
+        budget: &mut Budget,
+    ) -> Search<VillagerActionEnum> {
+        let owner = current_state.villager.id;
+        let mut thinking = self.thinking.take().unwrap_or_else(|| {
+            Arc::make_mut(&mut current_state.reservations).release(owner);
+            // Plans only go by what the villager has seen for itself.
+            let perceived = current_state.perceived();
+            let goals = by_priority(&perceived, &self.goals)
+                .into_iter()
+                .filter(|&i| self.retry_at[i] <= now)
+                .collect();
+            Box::new(Thinking {
+                perceived,
+                goals,
+                planning: None,
+            })
+        });
+
+        while let Some(&i) = thinking.goals.front() {
+            let planning = thinking
+                .planning
+                .get_or_insert_with(|| Planning::new(&thinking.perceived, self.goals[i].as_ref()));
+            match budget.search(planning) {
+                Search::Pending => {
+                    self.thinking = Some(thinking);
+                    return Search::Pending;
+                }
+                Search::Found(new_plan) => {
+                    Arc::make_mut(&mut current_state.reservations).claim_plan(
+                        owner,
+                        &new_plan,
+                        now + CLAIM_TTL,
+                    );
+                    discard(Some(thinking));
+                    return Search::Found(new_plan);
+                }
+                Search::Unreachable => {
+                    self.retry_at[i] = now + GIVE_UP_TICKS;
+                    thinking.goals.pop_front();
+                    discard(thinking.planning.take());
+                }
+            }
+        }
+
+        Search::Unreachable
+    }
+
+    fn reset(&mut self, current_state: &mut VillageState) {
+        discard(self.thinking.take());
+        if let Some(mut executor) = self.executor.take() {
+            executor.cancel(current_state);
+        }
+        let owner = current_state.villager.id;
+        Arc::make_mut(&mut current_state.reservations).release(owner);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::item::Item;
+    use crate::simulation::{Simulation, SimulationConfig};
+    use rand::rngs::mock::StepRng;
+
+    // Wants one more wood than the villager has.
+    struct FetchWood;
+
+    impl Goal<VillageState> for FetchWood {
+        fn priority(&self, _current_state: &VillageState) -> i64 {
+            1
+        }
+
+        fn goal_state(&self, current_state: VillageState) -> VillageState {
+            let mut goal_state = current_state;
+            goal_state.villager.inventory.push("wood".to_string());
+            goal_state
+        }
+    }
+
+    fn wood() -> Item {
+        Item::new("wood".to_string(), (6, 0))
+    }
+
+    // A village with nothing about but a bit of wood a short walk from the villager.
+    fn village() -> VillageState {
+        let mut state = Simulation::new(SimulationConfig::default()).into_state();
+        state.items = Arc::new(vec![wood()]);
+        state.wolves = Arc::new(vec![]);
+        state
+    }
+
+    // Ticks with all the planning budget the node could want.
+    fn tick(behaviour: &mut Behaviour, state: &mut VillageState) -> Status {
+        let mut budget = Budget::Expansions(usize::MAX);
+        behaviour.tick(state, 0, &mut StepRng::new(0, 1), &mut budget)
+    }
+
+    fn gain_flint(state: &mut VillageState) -> Status {
+        state.villager.inventory.push("flint".to_string());
+        Status::Succeeded
+    }
+
+    #[test]
+    fn a_higher_priority_branch_takes_over_from_a_running_plan() {
+        let mut state = village();
+        let mut behaviour = Behaviour::selector(vec![
+            Behaviour::sequence(vec![
+                Behaviour::Condition(|state| state.villager.is_starving()),
+                Behaviour::Action(gain_flint),
+            ]),
+            Behaviour::plan(vec![Box::new(FetchWood)]),
+        ]);
+
+        assert_eq!(tick(&mut behaviour, &mut state), Status::Running);
+        assert!(behaviour.progress().is_some());
+        assert!(state.reservations.is_claimed_by_other(&wood(), 1));
+
+        state.villager.satiety = 0;
+        assert_eq!(tick(&mut behaviour, &mut state), Status::Succeeded);
+        assert_eq!(state.villager.inventory, vec!["flint".to_string()]);
+        assert!(behaviour.progress().is_none());
+        assert!(!state.reservations.is_claimed_by_other(&wood(), 1));
+    }
+
+    #[test]
+    fn a_search_out_of_time_carries_on_the_next_tick() {
+        let mut state = village();
+        let mut behaviour = Behaviour::plan(vec![Box::new(FetchWood)]);
+
+        let mut budget = Budget::Until(Instant::now());
+        let status = behaviour.tick(&mut state, 0, &mut StepRng::new(0, 1), &mut budget);
+        assert_eq!(status, Status::Running);
+        assert!(behaviour.progress().is_none());
+
+        assert_eq!(tick(&mut behaviour, &mut state), Status::Running);
+        assert!(behaviour.progress().is_some());
+    }
+
+    #[test]
+    fn a_sequence_stops_at_the_first_failure() {
+        let mut state = village();
+        let mut behaviour = Behaviour::sequence(vec![
+            Behaviour::Action(gain_flint),
+            Behaviour::Condition(|state| state.villager.is_starving()),
+            Behaviour::Action(gain_flint),
+        ]);
+
+        assert_eq!(tick(&mut behaviour, &mut state), Status::Failed);
+        assert_eq!(state.villager.inventory, vec!["flint".to_string()]);
+    }
+
+    #[test]
+    fn the_inverter_swaps_success_and_failure_but_not_running() {
+        let mut state = village();
+        let mut inverted = |action: fn(&mut VillageState) -> Status| {
+            tick(
+                &mut Behaviour::invert(Behaviour::Action(action)),
+                &mut state,
+            )
+        };
+
+        assert_eq!(inverted(|_| Status::Succeeded), Status::Failed);
+        assert_eq!(inverted(|_| Status::Failed), Status::Succeeded);
+        assert_eq!(inverted(|_| Status::Running), Status::Running);
+    }
+}
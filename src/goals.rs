@@ -1,24 +1,57 @@
-use crate::{actions::VillageState, goap::Goal};
+use crate::actions::VillageState;
+use crate::item::Item;
+use crate::villager::{VillagerId, HUNGRY, MAX_SATIETY};
+use outbound_goap::Goal;
+use std::sync::Arc;
+
+fn count(current_state: &VillageState, id: &str) -> usize {
+    current_state
+        .villager
+        .inventory
+        .iter()
+        .filter(|i| *i == id)
+        .count()
+}
+
+// A goal state asking for nothing to be carried or stocked, by anyone, for goals to add what they do want to. Plans
+// are then free to use up inventories and stock along the way.
+fn wanting(current_state: VillageState) -> VillageState {
+    let mut goal_state = current_state;
+    goal_state.villager.inventory = vec![];
+    goal_state.stockpile.contents = vec![];
+    for other in Arc::make_mut(&mut goal_state.others) {
+        other.inventory = vec![];
+    }
+    goal_state
+}
+
+// The goal is met by the stockpile holding one more `id` than now. Nothing else is asked for, so plans are free to use
+// up the villager's inventory (or other stock) along the way.
+fn one_more_stocked(current_state: VillageState, id: &str) -> VillageState {
+    let mut goal_state = wanting(current_state.clone());
+    goal_state.stockpile.contents = vec![id.to_string(); current_state.stockpile.count(id) + 1];
+    goal_state
+}
+
+// The goal is met by carrying one more `id` than now. Nothing else is asked for, so plans are free to use up the
+// villager's inventory along the way.
+fn one_more(current_state: VillageState, id: &str) -> VillageState {
+    let mut goal_state = wanting(current_state.clone());
+    goal_state.villager.inventory = vec![id.to_string(); count(&current_state, id) + 1];
+    goal_state
+}
 
 #[derive(Debug)]
 pub(crate) struct CollectWood {}
 
 impl Goal<VillageState> for CollectWood {
     fn priority(&self, current_state: &VillageState) -> i64 {
-        // Aiming to have 10 wood in inventory!
-        let wood_count = current_state
-            .villager
-            .inventory
-            .iter()
-            .filter(|i| *i == "wood")
-            .count() as i64;
-        10 - wood_count
+        // Aiming to have 10 wood in the stockpile!
+        10 - current_state.stockpile.count("wood") as i64
     }
 
     fn goal_state(&self, current_state: VillageState) -> VillageState {
-        let mut goal_state = current_state.clone();
-        goal_state.villager.inventory.push("wood".to_string());
-        goal_state
+        one_more_stocked(current_state, "wood")
     }
 }
 
@@ -27,41 +60,270 @@ pub(crate) struct CollectStone {}
 
 impl Goal<VillageState> for CollectStone {
     fn priority(&self, current_state: &VillageState) -> i64 {
-        // Aiming to have 10 stone in inventory!
-        let stone_count = current_state
+        // Aiming to have 10 stone in the stockpile!
+        10 - current_state.stockpile.count("stone") as i64
+    }
+
+    fn goal_state(&self, current_state: VillageState) -> VillageState {
+        one_more_stocked(current_state, "stone")
+    }
+}
+
+// Anything edible the villager carries.
+fn food(current_state: &VillageState) -> impl Iterator<Item = &String> {
+    current_state
+        .villager
+        .inventory
+        .iter()
+        .filter(|id| current_state.catalogue.nourishment(id).is_some())
+}
+
+#[derive(Debug)]
+pub(crate) struct CollectFood {}
+
+// Enough food to see the villager through the next few bouts of hunger.
+const FOOD_RESERVE: usize = 3;
+
+impl Goal<VillageState> for CollectFood {
+    fn priority(&self, current_state: &VillageState) -> i64 {
+        // More urgent the emptier the villager's stomach and its pockets, and pointless with nothing to eat about.
+        let carried = food(current_state).count();
+        if carried >= FOOD_RESERVE || current_state.catalogue.foods().is_empty() {
+            return 0;
+        }
+        let hunger = (MAX_SATIETY - current_state.villager.satiety) as i64;
+        10 + hunger / 10 - carried as i64
+    }
+
+    // The most filling food there is.
+    fn goal_state(&self, current_state: VillageState) -> VillageState {
+        let wanted = current_state
+            .catalogue
+            .foods()
+            .first()
+            .map(|(id, _)| id.to_string());
+        match wanted {
+            Some(id) => one_more(current_state, &id),
+            None => wanting(current_state),
+        }
+    }
+}
+
+// Makes sure the villager carries a `tool`, crafting one if need be, and a spare once the one it has is about to
+// break.
+#[derive(Debug)]
+pub(crate) struct HaveTool {
+    pub(crate) tool: &'static str,
+}
+
+// Uses left in a tool at which it's time to see about another.
+const WORN_OUT: u32 = 3;
+
+impl HaveTool {
+    fn wanted(&self, current_state: &VillageState) -> usize {
+        let villager = &current_state.villager;
+        let carried = count(current_state, self.tool);
+        let worn = villager
+            .uses_left(&current_state.catalogue, self.tool)
+            .is_some_and(|left| left <= WORN_OUT);
+        if carried == 0 || (carried == 1 && worn) {
+            carried + 1
+        } else {
+            carried
+        }
+    }
+}
+
+impl Goal<VillageState> for HaveTool {
+    fn priority(&self, current_state: &VillageState) -> i64 {
+        // Wanted whenever the villager has none of the tool, or only one that's nearly worn out.
+        if self.wanted(current_state) > count(current_state, self.tool) {
+            5
+        } else {
+            0
+        }
+    }
+
+    fn goal_state(&self, current_state: VillageState) -> VillageState {
+        let wanted = self.wanted(&current_state);
+        let mut goal_state = wanting(current_state);
+        goal_state.villager.inventory = vec![self.tool.to_string(); wanted];
+        goal_state
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct SateHunger {}
+
+impl Goal<VillageState> for SateHunger {
+    fn priority(&self, current_state: &VillageState) -> i64 {
+        // Nothing matters more than a meal once the villager is hungry.
+        if current_state.villager.satiety < HUNGRY {
+            100
+        } else {
+            0
+        }
+    }
+
+    fn goal_state(&self, current_state: VillageState) -> VillageState {
+        let mut goal_state = wanting(current_state.clone());
+        goal_state.villager.satiety = current_state.villager.satiety.saturating_add(1);
+        goal_state
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct BuildVillage {}
+
+impl Goal<VillageState> for BuildVillage {
+    fn priority(&self, current_state: &VillageState) -> i64 {
+        // Something to be getting on with whenever there's a site waiting to be built.
+        if current_state.sites.is_empty() {
+            0
+        } else {
+            5
+        }
+    }
+
+    fn goal_state(&self, current_state: VillageState) -> VillageState {
+        let mut goal_state = wanting(current_state.clone());
+        Arc::make_mut(&mut goal_state.buildings).push(current_state.sites[0].clone());
+        goal_state
+    }
+}
+
+// Puts back the trees that get cut down, one sapling at a time.
+#[derive(Debug)]
+pub(crate) struct PlantTrees {}
+
+impl Goal<VillageState> for PlantTrees {
+    fn priority(&self, current_state: &VillageState) -> i64 {
+        // Something to get round to whenever there's a sapling (or any other seedling) to hand or put by.
+        if current_state.has_seedling() || current_state.stocked_seedling().is_some() {
+            3
+        } else {
+            0
+        }
+    }
+
+    fn goal_state(&self, current_state: VillageState) -> VillageState {
+        let seedling = current_state
             .villager
             .inventory
             .iter()
-            .filter(|i| *i == "stone")
-            .count() as i64;
-        10 - stone_count
+            .find(|id| current_state.catalogue.is_seedling(id))
+            .map(String::as_str)
+            .or(current_state.stocked_seedling())
+            .map(str::to_string);
+        let mut goal_state = wanting(current_state.clone());
+        if let Some(id) = seedling {
+            Arc::make_mut(&mut goal_state.items)
+                .push(Item::new(id, current_state.villager.position));
+        }
+        goal_state
+    }
+}
+
+// Sees that nobody else goes hungry for want of something to eat, when the villager has food to share.
+#[derive(Debug)]
+pub(crate) struct ShareFood {}
+
+impl ShareFood {
+    // Someone going hungry, and what the villager has that they could eat.
+    fn hungry_neighbour(current_state: &VillageState) -> Option<(VillagerId, String)> {
+        food(current_state).find_map(|id| {
+            current_state
+                .others
+                .iter()
+                .find(|other| other.needs(&current_state.catalogue, id))
+                .map(|other| (other.id, id.clone()))
+        })
+    }
+}
+
+impl Goal<VillageState> for ShareFood {
+    fn priority(&self, current_state: &VillageState) -> i64 {
+        // Ahead of any chores, but not of the villager's own hunger.
+        if Self::hungry_neighbour(current_state).is_some() {
+            20
+        } else {
+            0
+        }
     }
 
     fn goal_state(&self, current_state: VillageState) -> VillageState {
-        let mut goal_state = current_state.clone();
-        goal_state.villager.inventory.push("stone".to_string());
+        let neighbour = Self::hungry_neighbour(&current_state);
+        let mut goal_state = wanting(current_state);
+        if let Some((id, food)) = neighbour {
+            let others = Arc::make_mut(&mut goal_state.others);
+            if let Some(other) = others.iter_mut().find(|other| other.id == id) {
+                other.inventory = vec![food];
+            }
+        }
         goal_state
     }
 }
 
+// Goes and sees what's out there, for want of anything better to do. Wanting for something that can't be found
+// nearby leaves the villager with nothing better to do, so this is also how it comes across more.
 #[derive(Debug)]
-pub(crate) struct CollectBerries {}
+pub(crate) struct Explore {}
 
-impl Goal<VillageState> for CollectBerries {
+impl Goal<VillageState> for Explore {
     fn priority(&self, current_state: &VillageState) -> i64 {
-        // Aiming to have 10 berries in inventory!
-        let berries_count = current_state
+        // Only ever planned for when nothing else can be.
+        if current_state
             .villager
-            .inventory
-            .iter()
-            .filter(|i| *i == "berry")
-            .count() as i64;
-        10 - berries_count
+            .map
+            .frontier(&current_state.world)
+            .is_empty()
+        {
+            0
+        } else {
+            1
+        }
     }
 
     fn goal_state(&self, current_state: VillageState) -> VillageState {
-        let mut goal_state = current_state.clone();
-        goal_state.villager.inventory.push("berry".to_string());
+        let mut goal_state = wanting(current_state.clone());
+        let world = &current_state.world;
+        if let Some(&unseen) = current_state.villager.map.frontier(world).first() {
+            goal_state.villager.map.reveal(world, unseen);
+        }
+        goal_state
+    }
+}
+
+// Deals with a wolf closing in: seeing it off, or running back to the village out of its reach when the villager is
+// in no shape for a fight.
+#[derive(Debug)]
+pub(crate) struct Defend {}
+
+// Health a villager needs to stand its ground against a wolf.
+const FIGHTING_FIT: u8 = 50;
+
+impl Goal<VillageState> for Defend {
+    fn priority(&self, current_state: &VillageState) -> i64 {
+        // Staying alive comes before anything, a meal included.
+        if current_state.threat().is_some() {
+            200
+        } else {
+            0
+        }
+    }
+
+    fn goal_state(&self, current_state: VillageState) -> VillageState {
+        let threat = current_state.threat().map(|wolf| wolf.id);
+        let fit = current_state
+            .villager
+            .health
+            .check()
+            .is_some_and(|health| health >= FIGHTING_FIT);
+        let mut goal_state = wanting(current_state);
+        Arc::make_mut(&mut goal_state.wolves).retain(|wolf| Some(wolf.id) != threat);
+        if !fit {
+            goal_state.villager.position = goal_state.stockpile.centre();
+        }
         goal_state
     }
 }
@@ -1,11 +1,190 @@
+use crate::assets;
+use serde::Deserialize;
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Item {
     pub position: (i64, i64),
     pub id: String,
+    /// How much is left to be had from it, for resources that are worked a bit at a time.
+    pub amount: u32,
+    /// Uses had out of it, for a tool put down part worn.
+    pub wear: u32,
 }
 
 impl Item {
     pub fn new(id: String, position: (i64, i64)) -> Item {
-        Item { id, position }
+        Item {
+            id,
+            position,
+            amount: 1,
+            wear: 0,
+        }
+    }
+
+    pub fn with_amount(self, amount: u32) -> Item {
+        Item { amount, ..self }
+    }
+
+    pub fn with_wear(self, wear: u32) -> Item {
+        Item { wear, ..self }
+    }
+
+    // Whether `other` is this same item, however much has been had from either since.
+    pub(crate) fn is(&self, other: &Item) -> bool {
+        self.id == other.id && self.position == other.position
+    }
+}
+
+/// Everything there is to know about one kind of item. What the kind can be worked into lives with the work itself,
+/// in the `Task`s.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Deserialize)]
+pub struct ItemKind {
+    pub id: String,
+    /// Whether it can be picked up off the ground and carried about, rather than staying put.
+    #[serde(default)]
+    pub pickable: bool,
+    /// How heavy one is to carry.
+    #[serde(default = "lightest")]
+    pub weight: u32,
+    /// What it's drawn as, in RGB.
+    pub colour: (u8, u8, u8),
+    /// How many are scattered about a new world for every 10,000 tiles of it.
+    #[serde(default)]
+    pub density: u32,
+    /// How much a fresh one holds, for resources that are worked a bit at a time.
+    #[serde(default = "one")]
+    pub amount: u32,
+    /// Ticks it takes to grow back one of its `amount` once some has been had. Resources that don't grow back are
+    /// gone once they're used up.
+    #[serde(default)]
+    pub refill: Option<u64>,
+    /// How many uses a tool stands up to before it breaks. Anything without one never wears out.
+    #[serde(default)]
+    pub durability: Option<u32>,
+    /// What a swing with it does to a wolf, for anything that makes a weapon.
+    #[serde(default)]
+    pub damage: Option<u8>,
+    /// How filling one is to eat, for anything edible.
+    #[serde(default)]
+    pub nourishment: Option<u8>,
+    /// What it grows into next and about how many ticks that takes, for anything still growing.
+    #[serde(default)]
+    pub grows: Option<(String, u32)>,
+}
+
+fn lightest() -> u32 {
+    1
+}
+
+fn one() -> u32 {
+    1
+}
+
+impl ItemKind {
+    /// Parse a list of item kinds written in RON, as in `assets/items.ron`.
+    pub fn parse_all(text: &str) -> Result<Vec<ItemKind>, ron::error::SpannedError> {
+        ron::from_str(text)
+    }
+
+    /// The item kinds the game ships with, as in `assets/items.ron`.
+    pub fn defaults() -> Vec<ItemKind> {
+        assets::shipped(assets::ITEMS)
+    }
+}
+
+/// Every kind of item in the game, looked up by id. Anything it doesn't know of is taken to be a light, plain grey
+/// thing that can be picked up.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Catalogue {
+    kinds: Vec<ItemKind>,
+}
+
+impl Catalogue {
+    pub fn new(kinds: Vec<ItemKind>) -> Self {
+        Self { kinds }
+    }
+
+    pub fn kinds(&self) -> &[ItemKind] {
+        &self.kinds
+    }
+
+    pub fn kind(&self, id: &str) -> Option<&ItemKind> {
+        self.kinds.iter().find(|kind| kind.id == id)
+    }
+
+    pub fn is_pickable(&self, id: &str) -> bool {
+        self.kind(id).is_none_or(|kind| kind.pickable)
+    }
+
+    pub fn weight(&self, id: &str) -> u32 {
+        self.kind(id).map_or(lightest(), |kind| kind.weight)
+    }
+
+    pub fn amount(&self, id: &str) -> u32 {
+        self.kind(id).map_or(one(), |kind| kind.amount)
+    }
+
+    pub fn refill(&self, id: &str) -> Option<u64> {
+        self.kind(id).and_then(|kind| kind.refill)
+    }
+
+    pub fn durability(&self, id: &str) -> Option<u32> {
+        self.kind(id).and_then(|kind| kind.durability)
+    }
+
+    pub fn damage(&self, id: &str) -> Option<u8> {
+        self.kind(id).and_then(|kind| kind.damage)
+    }
+
+    pub fn nourishment(&self, id: &str) -> Option<u8> {
+        self.kind(id).and_then(|kind| kind.nourishment)
+    }
+
+    /// Everything edible and how filling it is, most filling first.
+    pub fn foods(&self) -> Vec<(&str, u8)> {
+        let mut foods: Vec<_> = self
+            .kinds
+            .iter()
+            .filter_map(|kind| Some((kind.id.as_str(), kind.nourishment?)))
+            .collect();
+        foods.sort_by_key(|&(_, nourishment)| std::cmp::Reverse(nourishment));
+        foods
+    }
+
+    pub fn grows(&self, id: &str) -> Option<(&str, u32)> {
+        self.kind(id)
+            .and_then(|kind| kind.grows.as_ref())
+            .map(|(next, ticks)| (next.as_str(), *ticks))
+    }
+
+    /// Whether `id` is the first stage of something growing, i.e. what a villager plants.
+    pub fn is_seedling(&self, id: &str) -> bool {
+        self.grows(id).is_some()
+            && !self
+                .kinds
+                .iter()
+                .any(|kind| self.grows(&kind.id).is_some_and(|(next, _)| next == id))
+    }
+
+    /// Everything that can be carried about, in the order it's listed.
+    pub fn pickables(&self) -> impl Iterator<Item = &str> {
+        self.kinds
+            .iter()
+            .filter(|kind| kind.pickable)
+            .map(|kind| kind.id.as_str())
+    }
+
+    pub fn colour(&self, id: &str) -> (u8, u8, u8) {
+        self.kind(id).map_or((128, 128, 128), |kind| kind.colour)
+    }
+
+    /// The heaviest thing there is to carry, so a villager with this much room to spare can take anything.
+    pub fn heaviest(&self) -> u32 {
+        self.kinds
+            .iter()
+            .filter(|kind| kind.pickable)
+            .map(|kind| kind.weight)
+            .max()
+            .unwrap_or(lightest())
     }
 }
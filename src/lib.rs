@@ -1,93 +1,77 @@
 mod actions;
+mod assets;
+mod behaviour;
 mod goals;
-mod goap;
 mod item;
+mod map;
+mod movement;
+mod recipe;
+mod reservation;
+mod simulation;
+mod stockpile;
+mod structure;
+mod task;
 mod villager;
-
-use crate::actions::VillageState;
-use crate::goap::Action;
-use crate::goap::{plan, print_plan};
-use crate::item::Item;
-use crate::villager::Villager;
-use actions::VillagerActionEnum;
-use goals::{CollectBerries, CollectStone, CollectWood};
+mod wear;
+mod wildlife;
+mod world;
+
+pub use crate::assets::AssetError;
+pub use crate::item::{Catalogue, Item, ItemKind};
+pub use crate::map::{Map, REGION_SIZE};
+pub use crate::recipe::Recipe;
+pub use crate::simulation::{PlanningBudget, Progress, Simulation, SimulationConfig};
+pub use crate::stockpile::Stockpile;
+pub use crate::structure::{Blueprint, Building};
+pub use crate::task::{Byproduct, Task};
+pub use crate::villager::{Health, Villager, VillagerId};
+pub use crate::wear::Wear;
+pub use crate::wildlife::{Wolf, WolfId};
+pub use crate::world::{Terrain, World};
 use raylib::consts::KeyboardKey::*;
 use raylib::prelude::*;
 
-const MAX_BUILDINGS: usize = 100;
-const MAX_TREES: usize = 250;
-const MAX_BERRIES: usize = 50;
-const MAX_STONE: usize = 25;
+// The config the game runs with, read from the data files in `assets/` so that changes to them show up without a
+// rebuild.
+fn load_config() -> Result<SimulationConfig, AssetError> {
+    Ok(SimulationConfig {
+        items: assets::load("items.ron", assets::ITEMS)?,
+        recipes: assets::load("recipes.ron", assets::RECIPES)?,
+        blueprints: assets::load("structures.ron", assets::STRUCTURES)?,
+        tasks: assets::load("tasks.ron", assets::TASKS)?,
+        ..Default::default()
+    })
+}
+
+// Number keys for seeding whatever can be carried about, in catalogue order.
+const DROP_KEYS: [KeyboardKey; 9] = [
+    KEY_ONE, KEY_TWO, KEY_THREE, KEY_FOUR, KEY_FIVE, KEY_SIX, KEY_SEVEN, KEY_EIGHT, KEY_NINE,
+];
 
 pub fn run() {
-    let villager = Villager::default();
-    let villager_is_alive = villager.is_alive();
-    let villager_rect = Rectangle::new(10.0, 25.0, 10.0, 10.0);
+    let config = match load_config() {
+        Ok(config) => config,
+        Err(error) => {
+            eprintln!("{error}");
+            return;
+        }
+    };
 
-    println!("Hello, world! Villager is alive: {villager_is_alive}");
+    let villager_rect = Rectangle::new(10.0, 25.0, 10.0, 10.0);
 
     let (w, h) = (860, 640);
     let (mut rl, thread) = raylib::init().size(w, h).title("Outbound").build();
     rl.set_target_fps(60);
 
-    let mut items = Vec::with_capacity(MAX_BERRIES + MAX_STONE + MAX_TREES);
-    for _ in 0..MAX_TREES {
-        let rx: i64 = rl.get_random_value(0..150);
-        let ry: i64 = rl.get_random_value(0..150);
-        items.push(Item::new("tree".to_string(), (rx, ry)));
-    }
-    for _ in 0..MAX_BERRIES {
-        let rx: i64 = rl.get_random_value(0..150);
-        let ry: i64 = rl.get_random_value(0..150);
-        items.push(Item::new("berry".to_string(), (rx, ry)));
-    }
-    for _ in 0..MAX_STONE {
-        let rx: i64 = rl.get_random_value(0..150);
-        let ry: i64 = rl.get_random_value(0..150);
-        items.push(Item::new("stone".to_string(), (rx, ry)));
-    }
-
-    let current_state = VillageState { villager, items };
-
-    let villager_goals: Vec<Box<dyn goap::Goal<VillageState>>> = vec![
-        Box::new(CollectWood {}),
-        Box::new(CollectStone {}),
-        Box::new(CollectBerries {}),
-    ];
+    let mut simulation = Simulation::new(SimulationConfig {
+        seed: rl.get_random_value::<i32>(0..i32::MAX) as u64,
+        ..config
+    });
 
-    let plan_option = plan(current_state.clone(), &villager_goals);
-
-    let mut state = current_state.clone();
-    let mut plana: Vec<VillagerActionEnum> = plan_option.expect("FAILED TO PLAN");
-
-    print_plan(plana.clone());
-
-    let mut plan_iter = plana.iter();
-
-    let building_site = Rectangle::new(30.0, 20.0, 25.0, 25.0);
+    let villager_is_alive = simulation.villager().is_alive();
+    println!("Hello, world! Villager is alive: {villager_is_alive}");
 
     let mut player = Rectangle::new(400.0, 280.0, 40.0, 40.0);
-    let mut buildings = Vec::with_capacity(MAX_BUILDINGS);
-    let mut build_colors = Vec::with_capacity(MAX_BUILDINGS);
-    let mut spacing = 0.0;
-    for i in 0..MAX_BUILDINGS {
-        let bh: i32 = rl.get_random_value(100..800);
-        buildings.push(Rectangle::new(
-            -6000.0 + spacing,
-            (h - 130 - bh) as f32,
-            rl.get_random_value::<i32>(50..200) as f32,
-            bh as f32,
-        ));
-
-        spacing += buildings[i].width;
-        build_colors.push(Color::new(
-            rl.get_random_value::<i32>(200..240) as u8,
-            rl.get_random_value::<i32>(200..240) as u8,
-            rl.get_random_value::<i32>(200..240) as u8,
-            255,
-        ));
-    }
-
     let mut camera = Camera2D {
         target: Vector2::new(player.x + 20.0, player.y + 20.0),
         offset: Vector2::new(player.x, player.y),
@@ -95,44 +79,26 @@ pub fn run() {
         zoom: 1.0,
     };
 
-    let mut movement_left = vec![];
     let mut act_offset = 0;
 
     while !rl.window_should_close() {
+        if rl.is_key_pressed(KEY_SPACE) {
+            simulation.interrupt();
+        }
+
+        // Seed resources under the mouse for the villagers to find.
+        let dropped = DROP_KEYS
+            .iter()
+            .zip(simulation.catalogue().pickables())
+            .find(|(&key, _)| rl.is_key_pressed(key))
+            .map(|(_, id)| id.to_string());
+        if let Some(id) = dropped {
+            let at = rl.get_screen_to_world2D(rl.get_mouse_position(), camera);
+            simulation.drop_item(&id, (at.x as i64, at.y as i64));
+        }
+
         if act_offset % 10 == 0 {
-            if let Some(p) = movement_left.pop() {
-                state.villager.position = p;
-            } else if let Some(current_action) = plan_iter.next() {
-                if let crate::actions::VillagerActionEnum::MoveToNearestItem(move_action) =
-                    current_action
-                {
-                    let goal_state = move_action.act(state.clone());
-                    let (gx, gy) = state.villager.position;
-
-                    if let Some((movement, _)) = pathfinding::directed::astar::astar(
-                        &goal_state.villager.position,
-                        |(x, y)| -> Vec<((i64, i64), i32)> {
-                            (-1..=1)
-                                .flat_map(move |i| (-1..=1).map(move |j| ((x + i, y + j), 1)))
-                                .collect()
-                        },
-                        |&(x, y)| -> i32 {
-                            (((gx - x) as f32).powf(2.0) + ((gy - y) as f32).powf(2.0)).sqrt()
-                                as i32
-                        },
-                        |&(x, y)| -> bool { x == gx && y == gy },
-                    ) {
-                        movement_left = movement;
-                    }
-                } else {
-                    state = current_action.act(state);
-                }
-            } else {
-                let plan_option = plan(state.clone(), &villager_goals);
-                plana = plan_option.expect("FAILED TO PLAN");
-                print_plan(plana.clone());
-                plan_iter = plana.iter();
-            }
+            simulation.tick();
         }
         act_offset += 1;
 
@@ -172,33 +138,103 @@ pub fn run() {
         d.clear_background(Color::RAYWHITE);
         {
             let mut d2 = d.begin_mode2D(camera);
-            d2.draw_rectangle(-6000, 320, 13000, 8000, Color::DARKGRAY);
+            let world = simulation.world();
+            for x in 0..world.width {
+                for y in 0..world.height {
+                    let c = match world.terrain((x, y)) {
+                        Terrain::Grass => continue,
+                        Terrain::Rough => Color::BEIGE,
+                        Terrain::Water => Color::SKYBLUE,
+                    };
+                    d2.draw_rectangle(x as i32, y as i32, 1, 1, c);
+                }
+            }
+            d2.draw_rectangle_lines(0, 0, world.width as i32, world.height as i32, Color::GRAY);
+
+            // Fog over anywhere nobody has been to see yet.
+            for x in (0..world.width).step_by(REGION_SIZE as usize) {
+                for y in (0..world.height).step_by(REGION_SIZE as usize) {
+                    if !simulation.is_explored((x, y)) {
+                        let size = REGION_SIZE as i32;
+                        d2.draw_rectangle(
+                            x as i32,
+                            y as i32,
+                            size,
+                            size,
+                            Color::LIGHTGRAY.alpha(0.6),
+                        );
+                    }
+                }
+            }
 
+            let stockpile = simulation.stockpile();
+            let building_site = Rectangle::new(
+                stockpile.corner.0 as f32,
+                stockpile.corner.1 as f32,
+                stockpile.width as f32,
+                stockpile.height as f32,
+            );
             d2.draw_rectangle_lines_ex(building_site, 1.0, Color::LIMEGREEN);
 
-            for i in 0..MAX_BUILDINGS {
-                d2.draw_rectangle_rec(buildings[i], build_colors[i]);
+            // Finished buildings are filled in, the ones still to be built just marked out.
+            let footprint = |building: &Building| {
+                let blueprint = simulation.blueprint(&building.blueprint)?;
+                Some(Rectangle::new(
+                    building.corner.0 as f32,
+                    building.corner.1 as f32,
+                    blueprint.width as f32,
+                    blueprint.height as f32,
+                ))
+            };
+            for rect in simulation.buildings().iter().filter_map(footprint) {
+                d2.draw_rectangle_rec(rect, Color::DARKBROWN);
+            }
+            for rect in simulation.sites().iter().filter_map(footprint) {
+                d2.draw_rectangle_lines_ex(rect, 0.5, Color::DARKBROWN);
             }
 
-            d2.draw_circle(
-                state.villager.position.0 as i32,
-                state.villager.position.1 as i32,
-                3.0,
-                Color::BLUE,
-            );
-
-            for i in state.items.clone().into_iter() {
-                let c = if &i.id == "tree" {
-                    Color::LAWNGREEN
-                } else if &i.id == "berry" {
-                    Color::PURPLE
-                } else if &i.id == "wood" {
-                    Color::BROWN
+            for villager in simulation.villagers() {
+                let c = if villager.is_alive() {
+                    Color::BLUE
                 } else {
                     Color::GRAY
                 };
+                d2.draw_circle(
+                    villager.position.0 as i32,
+                    villager.position.1 as i32,
+                    3.0,
+                    c,
+                );
+            }
+
+            for wolf in simulation.wolves() {
+                d2.draw_circle(
+                    wolf.position.0 as i32,
+                    wolf.position.1 as i32,
+                    3.0,
+                    Color::DARKGRAY,
+                );
+            }
+
+            for i in simulation.items() {
+                let (r, g, b) = simulation.catalogue().colour(&i.id);
+                let c = Color::new(r, g, b, 255);
                 d2.draw_circle(i.position.0 as i32, i.position.1 as i32, 2.0, c);
             }
+            // How far through its current action each villager is.
+            for villager in simulation.villagers() {
+                let Some(progress) = simulation.progress(villager.id) else {
+                    continue;
+                };
+                let (x, y) = (villager.position.0 as f32, villager.position.1 as f32);
+                let done = progress.elapsed as f32 / progress.duration.max(1) as f32;
+                d2.draw_rectangle_rec(Rectangle::new(x - 4.0, y - 6.0, 8.0, 1.0), Color::LIGHTGRAY);
+                d2.draw_rectangle_rec(
+                    Rectangle::new(x - 4.0, y - 6.0, 8.0 * done, 1.0),
+                    Color::GOLD,
+                );
+            }
+
             d2.draw_rectangle_rec(villager_rect, Color::BLUE);
             d2.draw_rectangle_rec(player, Color::RED);
             d2.draw_line(
@@ -224,8 +260,18 @@ pub fn run() {
         d.draw_rectangle(w - 5, 5, 5, h - 10, Color::RED);
         d.draw_rectangle(0, h - 5, w, 5, Color::RED);
 
-        d.draw_rectangle(10, 10, 250, 113, Color::SKYBLUE.alpha(0.5));
-        d.draw_rectangle_lines(10, 10, 250, 113, Color::BLUE);
+        // What each number key drops, a few to a line.
+        let droppable: Vec<_> = DROP_KEYS
+            .iter()
+            .zip(simulation.catalogue().pickables())
+            .enumerate()
+            .map(|(i, (_, id))| format!("{} {id}", i + 1))
+            .collect();
+        let drop_lines: Vec<_> = droppable.chunks(3).map(|chunk| chunk.join(", ")).collect();
+
+        let help_height = 153 + 15 * drop_lines.len() as i32;
+        d.draw_rectangle(10, 10, 250, help_height, Color::SKYBLUE.alpha(0.5));
+        d.draw_rectangle_lines(10, 10, 250, help_height, Color::BLUE);
 
         d.draw_text("Free 2d camera controls:", 20, 20, 10, Color::BLACK);
         d.draw_text("- Right/Left to move Offset", 40, 40, 10, Color::DARKGRAY);
@@ -238,5 +284,51 @@ pub fn run() {
             10,
             Color::DARKGRAY,
         );
+        d.draw_text("- Space to abandon the plan", 40, 120, 10, Color::DARKGRAY);
+        d.draw_text("- Number keys to drop:", 40, 140, 10, Color::DARKGRAY);
+        for (row, line) in drop_lines.iter().enumerate() {
+            d.draw_text(line, 50, 155 + 15 * row as i32, 10, Color::DARKGRAY);
+        }
+
+        let stockpile = simulation.stockpile();
+        let stocked: Vec<_> = simulation
+            .catalogue()
+            .kinds()
+            .iter()
+            .map(|kind| (stockpile.count(&kind.id), &kind.id))
+            .filter(|(count, _)| *count > 0)
+            .map(|(count, id)| format!("{count} {id}"))
+            .collect();
+        let stock = format!(
+            "Stockpile: {}. Buildings: {} built, {} to go",
+            if stocked.is_empty() {
+                "nothing".to_string()
+            } else {
+                stocked.join(", ")
+            },
+            simulation.buildings().len(),
+            simulation.sites().len(),
+        );
+        d.draw_text(&stock, 20, h - 30, 10, Color::BLACK);
+
+        for (row, villager) in simulation.villagers().enumerate() {
+            let doing = match simulation.progress(villager.id) {
+                _ if !villager.is_alive() => "dead".to_string(),
+                Some(progress) => format!(
+                    "{} ({}/{})",
+                    progress.activity, progress.elapsed, progress.duration
+                ),
+                None => "idle".to_string(),
+            };
+            let doing = format!(
+                "Villager {}: {}, health {}, carrying {}/{}",
+                villager.id,
+                doing,
+                villager.health.check().unwrap_or(0),
+                villager.load(simulation.catalogue()),
+                villager.capacity
+            );
+            d.draw_text(&doing, 20, h - 45 - 15 * row as i32, 10, Color::BLACK);
+        }
     }
 }
@@ -0,0 +1,112 @@
+// What a villager has seen of the world, kept a region at a time so it stays small enough to copy into every state
+// the planner looks at. Villagers only know about the items lying in regions they've seen.
+use crate::world::World;
+
+/// Regions are squares this many tiles across.
+pub const REGION_SIZE: i64 = 10;
+
+// How far a villager can see, in tiles, in any direction.
+const SIGHT: i64 = 12;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Map {
+    // A bit for each region, row by row.
+    seen: Vec<u64>,
+}
+
+fn regions_across(world: &World) -> i64 {
+    (world.width + REGION_SIZE - 1) / REGION_SIZE
+}
+
+fn regions_down(world: &World) -> i64 {
+    (world.height + REGION_SIZE - 1) / REGION_SIZE
+}
+
+fn region_of((x, y): (i64, i64)) -> (i64, i64) {
+    (x.div_euclid(REGION_SIZE), y.div_euclid(REGION_SIZE))
+}
+
+impl Map {
+    fn bit(world: &World, (rx, ry): (i64, i64)) -> Option<usize> {
+        let in_world =
+            (0..regions_across(world)).contains(&rx) && (0..regions_down(world)).contains(&ry);
+        in_world.then(|| (ry * regions_across(world) + rx) as usize)
+    }
+
+    fn has_seen_region(&self, world: &World, region: (i64, i64)) -> bool {
+        Self::bit(world, region).is_some_and(|bit| {
+            self.seen
+                .get(bit / 64)
+                .is_some_and(|word| word & (1 << (bit % 64)) != 0)
+        })
+    }
+
+    fn reveal_region(&mut self, world: &World, region: (i64, i64)) {
+        let Some(bit) = Self::bit(world, region) else {
+            return;
+        };
+        if self.seen.len() <= bit / 64 {
+            self.seen.resize(bit / 64 + 1, 0);
+        }
+        self.seen[bit / 64] |= 1 << (bit % 64);
+    }
+
+    /// Whether the region `position` lies in has been seen.
+    pub fn has_seen(&self, world: &World, position: (i64, i64)) -> bool {
+        self.has_seen_region(world, region_of(position))
+    }
+
+    /// How many regions have been seen.
+    pub fn regions_seen(&self) -> usize {
+        self.seen
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    // Marks the region `position` lies in as seen.
+    pub(crate) fn reveal(&mut self, world: &World, position: (i64, i64)) {
+        self.reveal_region(world, region_of(position));
+    }
+
+    // Takes in every region within sight of `position`.
+    pub(crate) fn look(&mut self, world: &World, (x, y): (i64, i64)) {
+        let (left, top) = region_of((x - SIGHT, y - SIGHT));
+        let (right, bottom) = region_of((x + SIGHT, y + SIGHT));
+        for rx in left..=right {
+            for ry in top..=bottom {
+                self.reveal_region(world, (rx, ry));
+            }
+        }
+    }
+
+    // Somewhere to stand in each unseen region bordering on a seen one: the edge of what the villager knows, and where
+    // to go to find out more.
+    pub(crate) fn frontier(&self, world: &World) -> Vec<(i64, i64)> {
+        let mut frontier = vec![];
+        for ry in 0..regions_down(world) {
+            for rx in 0..regions_across(world) {
+                if self.has_seen_region(world, (rx, ry)) {
+                    continue;
+                }
+                let borders_seen = (-1..=1)
+                    .flat_map(|i| (-1..=1).map(move |j| (rx + i, ry + j)))
+                    .any(|region| self.has_seen_region(world, region));
+                if !borders_seen {
+                    continue;
+                }
+                // The middle of the region, or failing that anywhere in it that can be stood on.
+                let (left, top) = (rx * REGION_SIZE, ry * REGION_SIZE);
+                let middle = (left + REGION_SIZE / 2, top + REGION_SIZE / 2);
+                let spot = std::iter::once(middle)
+                    .chain(
+                        (0..REGION_SIZE)
+                            .flat_map(|i| (0..REGION_SIZE).map(move |j| (left + i, top + j))),
+                    )
+                    .find(|&position| world.is_walkable(position));
+                frontier.extend(spot);
+            }
+        }
+        frontier
+    }
+}
@@ -0,0 +1,220 @@
+// How villagers get about: 8-way steps across the grid, each costing the terrain's move cost in ticks. The planner
+// and the executor both go through here, so a plan's cost is the time it really takes to walk and anything that can't
+// be reached is never planned for.
+use std::cell::RefCell;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+use crate::world::World;
+use pathfinding::directed::astar::astar;
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub(crate) struct Route {
+    // Every tile on the way, starting with the one the walker is stood on.
+    pub(crate) path: Vec<(i64, i64)>,
+    // Ticks it takes to walk the whole path.
+    pub(crate) cost: u64,
+}
+
+impl Route {
+    pub(crate) fn start(&self) -> (i64, i64) {
+        self.path[0]
+    }
+
+    pub(crate) fn destination(&self) -> (i64, i64) {
+        *self.path.last().expect("Routes always include their start")
+    }
+
+    // Where the walker has got to after `ticks` ticks on the route.
+    pub(crate) fn position_after(&self, world: &World, ticks: u64) -> (i64, i64) {
+        let mut spent = 0;
+        let mut position = self.start();
+        for &next in &self.path[1..] {
+            spent += step_cost(world, next).unwrap_or(u64::MAX);
+            if spent > ticks {
+                break;
+            }
+            position = next;
+        }
+        position
+    }
+}
+
+fn step_cost(world: &World, position: (i64, i64)) -> Option<u64> {
+    if world.contains(position) {
+        world.terrain(position).move_cost()
+    } else {
+        None
+    }
+}
+
+fn neighbours(world: &World, (x, y): (i64, i64)) -> Vec<((i64, i64), u64)> {
+    (-1..=1)
+        .flat_map(|i| (-1..=1).map(move |j| (x + i, y + j)))
+        .filter(|&next| next != (x, y))
+        .filter_map(|next| Some((next, step_cost(world, next)?)))
+        .collect()
+}
+
+// The fewest ticks it could take to walk between two tiles: every step costs at least a tick and diagonals are as
+// cheap as straight steps.
+pub(crate) fn distance(from: (i64, i64), to: (i64, i64)) -> u64 {
+    (to.0 - from.0)
+        .unsigned_abs()
+        .max((to.1 - from.1).unsigned_abs())
+}
+
+// Cheapest way from `from` to `to`, if there is one.
+pub(crate) fn route(world: &World, from: (i64, i64), to: (i64, i64)) -> Option<Route> {
+    if !world.is_walkable(to) {
+        return None;
+    }
+
+    astar(
+        &from,
+        |&position| neighbours(world, position),
+        |&position| distance(position, to),
+        |&position| position == to,
+    )
+    .map(|(path, cost)| Route { path, cost })
+}
+
+// Planning asks for the same floods over and over (every state with the villager stood in the same place and the same
+// items about), so recent answers are kept. Once the cache gets this big it is thrown away and started afresh.
+const FLOOD_CACHE_SIZE: usize = 4096;
+
+type FloodKey = (World, (i64, i64), Vec<Vec<(i64, i64)>>);
+
+thread_local! {
+    static FLOOD_CACHE: RefCell<HashMap<FloodKey, Vec<Option<Route>>>> = RefCell::new(HashMap::new());
+}
+
+// For each set of targets, the cheapest way from `from` to whichever of them is quickest to reach (`None` for sets
+// that can't be reached at all).
+pub(crate) fn routes_to_nearest(
+    world: &World,
+    from: (i64, i64),
+    targets: &[HashSet<(i64, i64)>],
+) -> Vec<Option<Route>> {
+    let key: FloodKey = (
+        *world,
+        from,
+        targets
+            .iter()
+            .map(|target| {
+                let mut positions: Vec<_> = target.iter().copied().collect();
+                positions.sort_unstable();
+                positions
+            })
+            .collect(),
+    );
+
+    if let Some(routes) = FLOOD_CACHE.with(|cache| cache.borrow().get(&key).cloned()) {
+        return routes;
+    }
+
+    // Sets with nothing in them can't be reached, and are left out so the flood can stop once the rest have been.
+    let wanted: Vec<usize> = (0..targets.len())
+        .filter(|&i| !targets[i].is_empty())
+        .collect();
+    let is_target: Vec<_> = wanted
+        .iter()
+        .map(|&i| {
+            let target = &targets[i];
+            move |position| target.contains(&position)
+        })
+        .collect();
+    let is_target: Vec<&dyn Fn((i64, i64)) -> bool> = is_target
+        .iter()
+        .map(|is_target| is_target as &dyn Fn((i64, i64)) -> bool)
+        .collect();
+    let mut routes = vec![None; targets.len()];
+    for (i, route) in wanted
+        .into_iter()
+        .zip(flood(world, from, &is_target, u64::MAX))
+    {
+        routes[i] = route;
+    }
+
+    FLOOD_CACHE.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        if cache.len() >= FLOOD_CACHE_SIZE {
+            cache.clear();
+        }
+        cache.insert(key, routes.clone());
+    });
+
+    routes
+}
+
+// The cheapest way from `from` to the nearest tile that's `wanted`, if there's one within `within` ticks. For targets
+// that are easier to say than to list, like anywhere clear.
+pub(crate) fn route_to_nearest(
+    world: &World,
+    from: (i64, i64),
+    wanted: &dyn Fn((i64, i64)) -> bool,
+    within: u64,
+) -> Option<Route> {
+    flood(world, from, &[wanted], within).pop().flatten()
+}
+
+// Dijkstra over the grid using flat arrays indexed by tile, for all the targets at once, going no further than `within`
+// ticks.
+fn flood(
+    world: &World,
+    from: (i64, i64),
+    targets: &[&dyn Fn((i64, i64)) -> bool],
+    within: u64,
+) -> Vec<Option<Route>> {
+    let mut routes = vec![None; targets.len()];
+    let mut unreached = targets.len();
+    if unreached == 0 || !world.contains(from) {
+        return routes;
+    }
+
+    let index = |(x, y): (i64, i64)| (y * world.width + x) as usize;
+    let tiles = (world.width * world.height) as usize;
+    let mut costs = vec![u64::MAX; tiles];
+    let mut came_from = vec![usize::MAX; tiles];
+    let mut frontier = BinaryHeap::new();
+
+    costs[index(from)] = 0;
+    frontier.push(Reverse((0, from)));
+
+    while let Some(Reverse((cost, position))) = frontier.pop() {
+        if cost > costs[index(position)] {
+            continue;
+        }
+        if cost > within {
+            break;
+        }
+
+        for (route, is_target) in routes.iter_mut().zip(targets) {
+            if route.is_none() && is_target(position) {
+                let mut path = vec![position];
+                let mut i = index(position);
+                while came_from[i] != usize::MAX {
+                    i = came_from[i];
+                    path.push(((i as i64) % world.width, (i as i64) / world.width));
+                }
+                path.reverse();
+                *route = Some(Route { path, cost });
+                unreached -= 1;
+            }
+        }
+        if unreached == 0 {
+            break;
+        }
+
+        for (next, step) in neighbours(world, position) {
+            let next_cost = cost + step;
+            if next_cost < costs[index(next)] {
+                costs[index(next)] = next_cost;
+                came_from[index(next)] = index(position);
+                frontier.push(Reverse((next_cost, next)));
+            }
+        }
+    }
+
+    routes
+}
@@ -0,0 +1,25 @@
+use crate::assets;
+use serde::Deserialize;
+
+/// Turns `inputs` from a villager's inventory into `outputs` over `duration` ticks, optionally only while stood at a
+/// `station`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Deserialize)]
+pub struct Recipe {
+    pub inputs: Vec<String>,
+    pub outputs: Vec<String>,
+    #[serde(default)]
+    pub station: Option<String>,
+    pub duration: u64,
+}
+
+impl Recipe {
+    /// Parse a list of recipes written in RON, as in `assets/recipes.ron`.
+    pub fn parse_all(text: &str) -> Result<Vec<Recipe>, ron::error::SpannedError> {
+        ron::from_str(text)
+    }
+
+    /// The recipes the game ships with, as in `assets/recipes.ron`.
+    pub fn defaults() -> Vec<Recipe> {
+        assets::shipped(assets::RECIPES)
+    }
+}
@@ -0,0 +1,118 @@
+use crate::actions::VillagerActionEnum;
+use crate::item::Item;
+use crate::villager::VillagerId;
+
+// Claims left behind by a villager that never finishes its plan (or never replans) are dropped after this many ticks.
+pub(crate) const CLAIM_TTL: u64 = 360;
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub(crate) struct Claim {
+    pub(crate) owner: VillagerId,
+    pub(crate) item: Item,
+    pub(crate) expires_at: u64,
+}
+
+// Items villagers have planned to use. Planners treat items claimed by someone else as if they were not there,
+// so several villagers planning over the same `items` don't all run to the same tree.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub(crate) struct Reservations {
+    claims: Vec<Claim>,
+}
+
+impl Reservations {
+    pub(crate) fn claim(&mut self, owner: VillagerId, item: Item, expires_at: u64) {
+        if let Some(claim) = self
+            .claims
+            .iter_mut()
+            .find(|claim| claim.owner == owner && claim.item.is(&item))
+        {
+            claim.expires_at = claim.expires_at.max(expires_at);
+        } else {
+            self.claims.push(Claim {
+                owner,
+                item,
+                expires_at,
+            });
+        }
+    }
+
+    // Claim every item the plan is going to consume.
+    pub(crate) fn claim_plan(
+        &mut self,
+        owner: VillagerId,
+        plan: &[VillagerActionEnum],
+        expires_at: u64,
+    ) {
+        for item in plan.iter().filter_map(VillagerActionEnum::target_item) {
+            self.claim(owner, item.clone(), expires_at);
+        }
+    }
+
+    pub(crate) fn is_claimed_by_other(&self, item: &Item, owner: VillagerId) -> bool {
+        self.claims
+            .iter()
+            .any(|claim| claim.owner != owner && claim.item.is(item))
+    }
+
+    // Drop every claim held by `owner`, e.g. when its plan has finished or failed.
+    pub(crate) fn release(&mut self, owner: VillagerId) {
+        self.claims.retain(|claim| claim.owner != owner);
+    }
+
+    pub(crate) fn expire(&mut self, now: u64) {
+        self.claims.retain(|claim| claim.expires_at > now);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tree() -> Item {
+        Item::new("tree".to_string(), (3, 4))
+    }
+
+    #[test]
+    fn claims_only_keep_other_villagers_off() {
+        let mut reservations = Reservations::default();
+        reservations.claim(0, tree(), 10);
+
+        assert!(!reservations.is_claimed_by_other(&tree(), 0));
+        assert!(reservations.is_claimed_by_other(&tree(), 1));
+        assert!(!reservations.is_claimed_by_other(&Item::new("tree".to_string(), (5, 4)), 1));
+    }
+
+    #[test]
+    fn release_drops_only_the_owners_claims() {
+        let mut reservations = Reservations::default();
+        reservations.claim(0, tree(), 10);
+        reservations.claim(1, Item::new("rock".to_string(), (1, 1)), 10);
+
+        reservations.release(0);
+
+        assert!(!reservations.is_claimed_by_other(&tree(), 2));
+        assert!(reservations.is_claimed_by_other(&Item::new("rock".to_string(), (1, 1)), 2));
+    }
+
+    #[test]
+    fn claims_expire_once_their_time_is_up() {
+        let mut reservations = Reservations::default();
+        reservations.claim(0, tree(), 10);
+
+        reservations.expire(9);
+        assert!(reservations.is_claimed_by_other(&tree(), 1));
+
+        reservations.expire(10);
+        assert!(!reservations.is_claimed_by_other(&tree(), 1));
+    }
+
+    #[test]
+    fn claiming_again_pushes_expiry_back() {
+        let mut reservations = Reservations::default();
+        reservations.claim(0, tree(), 10);
+        reservations.claim(0, tree(), 20);
+
+        reservations.expire(15);
+        assert!(reservations.is_claimed_by_other(&tree(), 1));
+    }
+}
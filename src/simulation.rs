@@ -0,0 +1,497 @@
+use crate::actions::Eat;
+use crate::actions::VillageState;
+use crate::behaviour::{Behaviour, Budget};
+use crate::goals::{
+    BuildVillage, CollectFood, CollectStone, CollectWood, Defend, Explore, HaveTool, PlantTrees,
+    SateHunger, ShareFood,
+};
+use crate::item::{Catalogue, Item, ItemKind};
+use crate::movement::distance;
+use crate::recipe::Recipe;
+use crate::reservation::Reservations;
+use crate::stockpile::Stockpile;
+use crate::structure::{Blueprint, Building};
+use crate::task::Task;
+use crate::villager::{Villager, VillagerId};
+use crate::wildlife::{prowl, Wolf, WolfId, SCENT};
+use crate::world::World;
+use outbound_goap::{Action, Goal, Status};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[derive(Clone, Debug)]
+pub struct SimulationConfig {
+    /// Worlds generated from the same config are identical.
+    pub seed: u64,
+    pub world: World,
+    /// How many villagers the village starts out with. There's always at least one, so 0 is taken as 1.
+    pub villagers: usize,
+    /// Every kind of item, including how thickly each is scattered about the world to begin with.
+    pub items: Vec<ItemKind>,
+    pub recipes: Vec<Recipe>,
+    pub blueprints: Vec<Blueprint>,
+    /// The work villagers know how to do on things in the world.
+    pub tasks: Vec<Task>,
+    pub stockpile: Stockpile,
+    /// Where the village means to build, in the order it gets round to them.
+    pub sites: Vec<Building>,
+    /// How many wolves roam the world, starting out well away from the village.
+    pub wolves: usize,
+    pub planning: PlanningBudget,
+}
+
+/// How much planning the villagers may do each tick, between them. Planning that takes more than this is carried on
+/// over the ticks that follow, which keeps each tick quick enough not to hold up the drawing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlanningBudget {
+    /// Stop once this much time has gone by. How far the planning gets each tick then comes down to how fast the
+    /// machine is, so runs from the same seed can turn out differently.
+    Time(Duration),
+    /// Stop after looking at this many states. Slow states make for slow ticks, but runs from the same seed always
+    /// turn out the same.
+    Expansions(usize),
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            world: World::default(),
+            villagers: 2,
+            items: ItemKind::defaults(),
+            recipes: Recipe::defaults(),
+            blueprints: Blueprint::defaults(),
+            tasks: Task::defaults(),
+            stockpile: Stockpile::default(),
+            sites: vec![
+                Building::new("hut", (31, 21)),
+                Building::new("hut", (31, 39)),
+                Building::new("storehouse", (46, 21)),
+            ],
+            wolves: 3,
+            planning: PlanningBudget::Time(Duration::from_millis(5)),
+        }
+    }
+}
+
+/// How far the villager has got with what it's doing.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Progress {
+    pub activity: String,
+    pub elapsed: u64,
+    pub duration: u64,
+}
+
+// Ticks between each pang of hunger.
+const HUNGER_INTERVAL: u64 = 10;
+
+/// The world and everyone in it, without any of the drawing. `run` renders one of these, but tests, benchmarks and
+/// servers can just as well drive it on their own with `tick`.
+pub struct Simulation {
+    state: VillageState,
+    // One for each villager, indexed by id.
+    brains: Vec<Behaviour>,
+    // How far each villager had got by the end of its last turn, indexed by id.
+    progress: Vec<Option<Progress>>,
+    ticks: u64,
+    rng: StdRng,
+    planning: PlanningBudget,
+}
+
+impl Simulation {
+    pub fn new(config: SimulationConfig) -> Self {
+        let mut rng = StdRng::seed_from_u64(config.seed);
+        let world = config.world.with_seed(config.seed);
+
+        let mut items = vec![];
+        let tiles = (world.width * world.height) as usize;
+        for kind in &config.items {
+            for _ in 0..kind.density as usize * tiles / 10_000 {
+                let Some(position) = world.random_position(&mut rng) else {
+                    break;
+                };
+                items.push(Item::new(kind.id.clone(), position).with_amount(kind.amount));
+            }
+        }
+
+        // Whatever the recipes have to be crafted at, set up as near the middle of the village as they'll go without
+        // getting in the way of anything to be built.
+        let mut stations: Vec<&str> = config
+            .recipes
+            .iter()
+            .filter_map(|recipe| recipe.station.as_deref())
+            .collect();
+        stations.sort_unstable();
+        stations.dedup();
+        let (stockpile, centre) = (&config.stockpile, config.stockpile.centre());
+        let (left, top) = stockpile.corner;
+        let mut spots: Vec<_> = (left..left + stockpile.width)
+            .flat_map(|x| (top..top + stockpile.height).map(move |y| (x, y)))
+            .filter(|&spot| {
+                !config.sites.iter().any(|site| {
+                    let blueprint = config.blueprints.iter().find(|b| b.name == site.blueprint);
+                    blueprint.is_some_and(|blueprint| blueprint.covers(site, spot))
+                })
+            })
+            .collect();
+        spots.sort_by_key(|&spot| distance(spot, centre));
+        for (station, spot) in stations.into_iter().zip(spots) {
+            items.push(Item::new(station.to_string(), spot));
+        }
+
+        // Out of scent of the village, if there's anywhere that far out.
+        let wolves = (0..config.wolves)
+            .filter_map(|_| {
+                std::iter::repeat_with(|| world.random_position(&mut rng))
+                    .take(100)
+                    .flatten()
+                    .find(|&position| config.stockpile.distance(position) > SCENT)
+            })
+            .enumerate()
+            .map(|(id, position)| Wolf::new(id as WolfId, position))
+            .collect();
+
+        // Kept sorted from here on, like inventories.
+        let mut stockpile = config.stockpile;
+        stockpile.contents.sort_unstable();
+
+        let mut state = VillageState {
+            world,
+            catalogue: Arc::new(Catalogue::new(config.items)),
+            recipes: config.recipes.into(),
+            blueprints: config.blueprints.into(),
+            tasks: config.tasks.into_iter().map(Arc::new).collect(),
+            villager: Villager::default(),
+            others: Arc::new(
+                (1..config.villagers)
+                    .map(|i| Villager {
+                        id: i as VillagerId,
+                        position: (i as i64, 0),
+                        ..Default::default()
+                    })
+                    .collect(),
+            ),
+            items: Arc::new(items),
+            stockpile,
+            sites: Arc::new(config.sites),
+            buildings: Arc::new(vec![]),
+            reservations: Arc::new(Reservations::default()),
+            wolves: Arc::new(wolves),
+        };
+
+        // Everyone starts out knowing their way round the village, and what they can see from where they stand.
+        let (world, village) = (state.world, state.stockpile.centre());
+        let others = Arc::make_mut(&mut state.others);
+        for villager in std::iter::once(&mut state.villager).chain(others) {
+            villager.map.look(&world, villager.position);
+            villager.map.look(&world, village);
+        }
+
+        let villagers = config.villagers.max(1);
+        let brains = (0..villagers).map(|_| brain()).collect();
+
+        Self {
+            state,
+            brains,
+            progress: vec![None; villagers],
+            ticks: 0,
+            rng,
+            planning: config.planning,
+        }
+    }
+
+    /// Advance the world by one step, each villager taking its turn in order of id.
+    pub fn tick(&mut self) {
+        // Shared out evenly, with whatever one villager doesn't need left over for the rest.
+        let mut planning = Budget::new(self.planning);
+        let villagers = self.brains.len();
+        for (id, brain) in self.brains.iter_mut().enumerate() {
+            let mut budget = planning.share(villagers - id);
+            self.state.focus(id as VillagerId);
+            brain.tick(&mut self.state, self.ticks, &mut self.rng, &mut budget);
+            planning.hand_back(budget);
+            self.progress[id] = brain.progress().map(|(action, elapsed)| Progress {
+                activity: action.activity().to_string(),
+                elapsed,
+                duration: action.duration(&self.state),
+            });
+            let villager = &mut self.state.villager;
+            villager.map.look(&self.state.world, villager.position);
+            villager.shed_wear();
+        }
+        self.state.focus(0);
+
+        self.ticks += 1;
+        if self.ticks % HUNGER_INTERVAL == 0 {
+            self.state.villager.get_hungry();
+            for other in Arc::make_mut(&mut self.state.others) {
+                other.get_hungry();
+            }
+        }
+        prowl(&mut self.state, self.ticks, &mut self.rng);
+        self.grow();
+        self.refill();
+        Arc::make_mut(&mut self.state.reservations).expire(self.ticks);
+    }
+
+    // Moves the odd growing tree on to its next stage.
+    fn grow(&mut self) {
+        let catalogue = &self.state.catalogue;
+        for item in Arc::make_mut(&mut self.state.items) {
+            let Some((next, ticks)) = catalogue.grows(&item.id) else {
+                continue;
+            };
+            if self.rng.gen_ratio(1, ticks.max(1)) {
+                item.id = next.to_string();
+                item.amount = catalogue.amount(next);
+            }
+        }
+    }
+
+    // Tops up anything that grows back, like berry bushes.
+    fn refill(&mut self) {
+        let catalogue = &self.state.catalogue;
+        for item in Arc::make_mut(&mut self.state.items) {
+            let Some(every) = catalogue.refill(&item.id) else {
+                continue;
+            };
+            if self.ticks % every.max(1) == 0 && item.amount < catalogue.amount(&item.id) {
+                item.amount += 1;
+            }
+        }
+    }
+
+    /// Make every villager drop whatever it is doing and think again.
+    pub fn interrupt(&mut self) {
+        for (id, brain) in self.brains.iter_mut().enumerate() {
+            self.state.focus(id as VillagerId);
+            brain.reset(&mut self.state);
+        }
+        self.state.focus(0);
+        self.progress.fill(None);
+    }
+
+    /// Put an item down in the world, as the player seeding resources for the villagers. Returns whether it could go
+    /// there, i.e. whether `position` is somewhere a villager could stand.
+    pub fn drop_item(&mut self, id: &str, position: (i64, i64)) -> bool {
+        if !self.state.world.is_walkable(position) {
+            return false;
+        }
+        Arc::make_mut(&mut self.state.items).push(Item::new(id.to_string(), position));
+        true
+    }
+
+    /// The action villager `id` is part way through, if it's carrying out a plan.
+    pub fn progress(&self, id: VillagerId) -> Option<&Progress> {
+        self.progress.get(id as usize)?.as_ref()
+    }
+
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    pub fn world(&self) -> &World {
+        &self.state.world
+    }
+
+    /// The first villager.
+    pub fn villager(&self) -> &Villager {
+        &self.state.villager
+    }
+
+    /// Everyone in the village, in order of id.
+    pub fn villagers(&self) -> impl Iterator<Item = &Villager> {
+        std::iter::once(&self.state.villager).chain(self.state.others.iter())
+    }
+
+    /// Every wolf still roaming the world.
+    pub fn wolves(&self) -> &[Wolf] {
+        &self.state.wolves
+    }
+
+    pub fn items(&self) -> &[Item] {
+        &self.state.items
+    }
+
+    pub fn stockpile(&self) -> &Stockpile {
+        &self.state.stockpile
+    }
+
+    /// Buildings yet to be put up.
+    pub fn sites(&self) -> &[Building] {
+        &self.state.sites
+    }
+
+    pub fn buildings(&self) -> &[Building] {
+        &self.state.buildings
+    }
+
+    pub fn blueprint(&self, name: &str) -> Option<&Blueprint> {
+        self.state.blueprint(name)
+    }
+
+    /// Whether any villager has seen the part of the world `position` lies in.
+    pub fn is_explored(&self, position: (i64, i64)) -> bool {
+        self.villagers()
+            .any(|villager| villager.map.has_seen(&self.state.world, position))
+    }
+
+    /// Every kind of item there is.
+    pub fn catalogue(&self) -> &Catalogue {
+        &self.state.catalogue
+    }
+
+    // The village as it stands, for tests to set up just so.
+    #[cfg(test)]
+    pub(crate) fn into_state(self) -> VillageState {
+        self.state
+    }
+}
+
+fn brain() -> Behaviour {
+    let villager_goals: Vec<Box<dyn Goal<VillageState>>> = vec![
+        Box::new(CollectWood {}),
+        Box::new(CollectStone {}),
+        Box::new(CollectFood {}),
+        Box::new(HaveTool { tool: "axe" }),
+        Box::new(HaveTool { tool: "pickaxe" }),
+        Box::new(BuildVillage {}),
+        Box::new(PlantTrees {}),
+        Box::new(ShareFood {}),
+        Box::new(SateHunger {}),
+        Box::new(Explore {}),
+    ];
+
+    Behaviour::selector(vec![
+        // The dead don't do much.
+        Behaviour::sequence(vec![
+            Behaviour::invert(Behaviour::Condition(|state| state.villager.is_alive())),
+            Behaviour::Action(idle),
+        ]),
+        // A starving villager eats whatever it has on it there and then.
+        Behaviour::sequence(vec![
+            Behaviour::Condition(|state| state.villager.is_starving()),
+            Behaviour::Action(eat),
+        ]),
+        // Out in the open with a wolf closing in, nothing else matters until that's dealt with.
+        Behaviour::sequence(vec![
+            Behaviour::Condition(|state| state.threat().is_some()),
+            Behaviour::plan(vec![Box::new(Defend {})]),
+        ]),
+        Behaviour::plan(villager_goals),
+        // Nothing to plan for, so wait around until something turns up.
+        Behaviour::Action(idle),
+    ])
+}
+
+fn idle(_: &mut VillageState) -> Status {
+    Status::Succeeded
+}
+
+fn eat(state: &mut VillageState) -> Status {
+    match Eat::from_inventory(state) {
+        Some(action) => {
+            *state = action.act(state.clone());
+            Status::Succeeded
+        }
+        None => Status::Failed,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(seed: u64) -> SimulationConfig {
+        SimulationConfig {
+            seed,
+            // So that runs from the same seed can be compared.
+            planning: PlanningBudget::Expansions(50),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn same_seed_same_village() {
+        let (mut one, mut other) = (Simulation::new(config(7)), Simulation::new(config(7)));
+        for _ in 0..50 {
+            one.tick();
+            other.tick();
+        }
+
+        assert!(one.villagers().eq(other.villagers()));
+        assert_eq!(one.items(), other.items());
+    }
+
+    #[test]
+    fn villagers_get_on_with_stocking_up() {
+        let mut simulation = Simulation::new(config(1));
+        for _ in 0..300 {
+            simulation.tick();
+        }
+
+        assert_eq!(simulation.ticks(), 300);
+        assert!(simulation.villagers().all(Villager::is_alive));
+        assert!(!simulation.stockpile().contents.is_empty());
+    }
+
+    #[test]
+    fn progress_is_reported_for_whatever_is_under_way() {
+        let mut simulation = Simulation::new(config(1));
+        for _ in 0..100 {
+            simulation.tick();
+        }
+
+        let progress: Vec<_> = simulation
+            .villagers()
+            .map(|v| simulation.progress(v.id))
+            .collect();
+        assert!(progress
+            .iter()
+            .flatten()
+            .all(|p| p.elapsed < p.duration.max(1)));
+        assert_eq!(simulation.progress(99), None);
+
+        simulation.interrupt();
+        assert!(simulation
+            .villagers()
+            .all(|v| simulation.progress(v.id).is_none()));
+    }
+
+    #[test]
+    fn a_world_with_nowhere_to_stand_stays_empty() {
+        let simulation = Simulation::new(SimulationConfig {
+            world: World::new(0, 0),
+            ..config(0)
+        });
+        assert_eq!(simulation.wolves().len(), 0);
+        assert!(simulation.items().iter().all(|item| item.id == "workbench"));
+    }
+
+    #[test]
+    fn stations_are_set_up_clear_of_the_building_sites() {
+        let mut config = config(0);
+        config.sites = vec![Building::new("hut", config.stockpile.centre())];
+        let simulation = Simulation::new(config);
+
+        let workbench = simulation
+            .items()
+            .iter()
+            .find(|item| item.id == "workbench")
+            .unwrap();
+        let hut = simulation.blueprint("hut").unwrap();
+        assert!(simulation.stockpile().contains(workbench.position));
+        assert!(!hut.covers(&simulation.sites()[0], workbench.position));
+    }
+
+    #[test]
+    fn there_is_always_a_villager() {
+        let simulation = Simulation::new(SimulationConfig {
+            villagers: 0,
+            ..config(0)
+        });
+        assert_eq!(simulation.villagers().count(), 1);
+    }
+}
@@ -0,0 +1,51 @@
+use crate::wear::Wear;
+
+/// Village storage that anyone can drop resources off at and take them back out of, covering a rectangle of tiles.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Stockpile {
+    /// Top left tile of the stockpile.
+    pub corner: (i64, i64),
+    pub width: i64,
+    pub height: i64,
+    pub contents: Vec<String>,
+    /// How worn the tools in `contents` are.
+    pub wear: Wear,
+}
+
+impl Stockpile {
+    pub fn contains(&self, (x, y): (i64, i64)) -> bool {
+        let (left, top) = self.corner;
+        (left..left + self.width).contains(&x) && (top..top + self.height).contains(&y)
+    }
+
+    pub fn centre(&self) -> (i64, i64) {
+        (
+            self.corner.0 + self.width / 2,
+            self.corner.1 + self.height / 2,
+        )
+    }
+
+    pub fn count(&self, id: &str) -> usize {
+        self.contents.iter().filter(|i| *i == id).count()
+    }
+
+    // The fewest steps it could possibly take to get onto the stockpile from `position`.
+    pub(crate) fn distance(&self, (x, y): (i64, i64)) -> u64 {
+        let (left, top) = self.corner;
+        let dx = (left - x).max(x - (left + self.width - 1)).max(0);
+        let dy = (top - y).max(y - (top + self.height - 1)).max(0);
+        dx.max(dy) as u64
+    }
+}
+
+impl Default for Stockpile {
+    fn default() -> Self {
+        Self {
+            corner: (30, 20),
+            width: 25,
+            height: 25,
+            contents: vec![],
+            wear: Wear::default(),
+        }
+    }
+}
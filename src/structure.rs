@@ -0,0 +1,47 @@
+use crate::assets;
+use serde::Deserialize;
+
+/// A kind of building and what it takes to put one up.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Deserialize)]
+pub struct Blueprint {
+    pub name: String,
+    pub materials: Vec<String>,
+    pub duration: u64,
+    pub width: i64,
+    pub height: i64,
+}
+
+impl Blueprint {
+    /// Parse a list of blueprints written in RON, as in `assets/structures.ron`.
+    pub fn parse_all(text: &str) -> Result<Vec<Blueprint>, ron::error::SpannedError> {
+        ron::from_str(text)
+    }
+
+    /// The blueprints the game ships with, as in `assets/structures.ron`.
+    pub fn defaults() -> Vec<Blueprint> {
+        assets::shipped(assets::STRUCTURES)
+    }
+
+    /// Whether `building`, put up from this blueprint, stands on `position`.
+    pub fn covers(&self, building: &Building, (x, y): (i64, i64)) -> bool {
+        let (left, top) = building.corner;
+        (left..left + self.width).contains(&x) && (top..top + self.height).contains(&y)
+    }
+}
+
+/// A building put up (or to be put up) from the named blueprint, with its top left tile at `corner`. Builders work
+/// from the corner.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Building {
+    pub blueprint: String,
+    pub corner: (i64, i64),
+}
+
+impl Building {
+    pub fn new(blueprint: &str, corner: (i64, i64)) -> Self {
+        Self {
+            blueprint: blueprint.to_string(),
+            corner,
+        }
+    }
+}
@@ -0,0 +1,267 @@
+use crate::assets;
+use serde::de::Error;
+use serde::{Deserialize, Deserializer};
+
+/// Work a villager can do on an item in the world, stood on it: felling a tree, mining a rock, foraging a bush.
+/// Tasks are read from `assets/tasks.ron`, so new ones can be added without touching the code.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Deserialize)]
+pub struct Task {
+    /// What the villager is said to be doing while at it, e.g. "chopping".
+    pub name: String,
+    /// The kind of item worked on, which each go at the task takes one of the amount of.
+    pub target: String,
+    /// Items the villager has to be carrying to do the work at all, such as tools. These aren't used up.
+    #[serde(default)]
+    pub requires: Vec<String>,
+    /// Items the work uses up from the villager's inventory.
+    #[serde(default)]
+    pub consumes: Vec<String>,
+    /// Ticks the work takes with nothing to speed it up.
+    pub duration: u64,
+    /// Items that get the work done quicker when carried, and how long it takes with each.
+    #[serde(default)]
+    pub quicker_with: Vec<(String, u64)>,
+    // Items left lying at the target, as `(id, least, most)` with the count decided once the work is done.
+    #[serde(default, deserialize_with = "ranges")]
+    drops: Vec<(String, usize, usize)>,
+    /// Items that go straight into the villager's inventory, when there's room for them.
+    #[serde(default)]
+    pub gives: Vec<String>,
+    /// Anything else that might come of the work, depending on luck and what the villager has on it.
+    #[serde(default)]
+    pub byproducts: Vec<Byproduct>,
+    // How often the work comes off, as `(chance, out_of)`.
+    #[serde(default = "always", deserialize_with = "odds")]
+    chance: (u32, u32),
+}
+
+/// Something extra that goes into the villager's inventory (when there's room for it) after a go at a task, under
+/// the right conditions. Only byproducts that are certain to come once their conditions hold are counted on when
+/// planning.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Deserialize)]
+pub struct Byproduct {
+    pub item: String,
+    /// Only comes along with the last of the target, rather than from every go.
+    #[serde(default)]
+    pub last: bool,
+    /// Items the villager has to be carrying for it to come, such as tools.
+    #[serde(default)]
+    pub with: Vec<String>,
+    /// Items the villager mustn't be carrying for it to come.
+    #[serde(default)]
+    pub without: Vec<String>,
+    // How often it comes when it can, as `(chance, out_of)`.
+    #[serde(default = "always", deserialize_with = "odds")]
+    chance: (u32, u32),
+}
+
+impl Byproduct {
+    /// A byproduct that comes with every go, whatever the villager has on it.
+    pub fn new(item: &str) -> Self {
+        Self {
+            item: item.to_string(),
+            last: false,
+            with: vec![],
+            without: vec![],
+            chance: always(),
+        }
+    }
+
+    /// Makes it come only `chance` times in `out_of`. Panics unless `0 < chance <= out_of`.
+    pub fn with_chance(self, chance: u32, out_of: u32) -> Self {
+        if let Err(error) = check_odds(chance, out_of) {
+            panic!("{error}");
+        }
+        Self {
+            chance: (chance, out_of),
+            ..self
+        }
+    }
+
+    /// How often it comes when it can, as `(chance, out_of)`.
+    pub fn chance(&self) -> (u32, u32) {
+        self.chance
+    }
+
+    /// Whether it can come for a villager carrying `inventory`, when the go at the task `spent` the target.
+    pub fn applies(&self, inventory: &[String], spent: bool) -> bool {
+        (spent || !self.last)
+            && self.with.iter().all(|tool| inventory.contains(tool))
+            && !self.without.iter().any(|tool| inventory.contains(tool))
+    }
+
+    /// Whether it's sure to come whenever it can.
+    pub fn is_certain(&self) -> bool {
+        self.chance.0 >= self.chance.1
+    }
+}
+
+fn always() -> (u32, u32) {
+    (1, 1)
+}
+
+// A `(chance, out_of)` has to be able to come off at all, and can't come off more than every time.
+fn check_odds(chance: u32, out_of: u32) -> Result<(), String> {
+    if chance == 0 || chance > out_of {
+        return Err(format!(
+            "a chance of {chance} out of {out_of} needs 0 < chance <= out_of"
+        ));
+    }
+    Ok(())
+}
+
+// A drop of `least` to `most` of something can't have fewer at most than at least.
+fn check_range(id: &str, least: usize, most: usize) -> Result<(), String> {
+    if least > most {
+        return Err(format!(
+            "a drop of {least} to {most} {id} needs least <= most"
+        ));
+    }
+    Ok(())
+}
+
+fn odds<'de, D: Deserializer<'de>>(deserializer: D) -> Result<(u32, u32), D::Error> {
+    let (chance, out_of) = <(u32, u32)>::deserialize(deserializer)?;
+    check_odds(chance, out_of).map_err(D::Error::custom)?;
+    Ok((chance, out_of))
+}
+
+fn ranges<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<(String, usize, usize)>, D::Error> {
+    let drops = <Vec<(String, usize, usize)>>::deserialize(deserializer)?;
+    for (id, least, most) in &drops {
+        check_range(id, *least, *most).map_err(D::Error::custom)?;
+    }
+    Ok(drops)
+}
+
+impl Task {
+    /// A task that always comes off and has nothing to show for it, to be built on.
+    pub fn new(name: &str, target: &str, duration: u64) -> Self {
+        Self {
+            name: name.to_string(),
+            target: target.to_string(),
+            requires: vec![],
+            consumes: vec![],
+            duration,
+            quicker_with: vec![],
+            drops: vec![],
+            gives: vec![],
+            byproducts: vec![],
+            chance: always(),
+        }
+    }
+
+    /// Leaves `least` to `most` of `id` lying at the target. Panics unless `least <= most`.
+    pub fn with_drop(mut self, id: &str, least: usize, most: usize) -> Self {
+        if let Err(error) = check_range(id, least, most) {
+            panic!("{error}");
+        }
+        self.drops.push((id.to_string(), least, most));
+        self
+    }
+
+    /// Makes the work come off only `chance` times in `out_of`. Panics unless `0 < chance <= out_of`.
+    pub fn with_chance(self, chance: u32, out_of: u32) -> Self {
+        if let Err(error) = check_odds(chance, out_of) {
+            panic!("{error}");
+        }
+        Self {
+            chance: (chance, out_of),
+            ..self
+        }
+    }
+
+    /// Items left lying at the target, as `(id, least, most)` with the count decided once the work is done.
+    pub fn drops(&self) -> &[(String, usize, usize)] {
+        &self.drops
+    }
+
+    /// How often the work comes off, as `(chance, out_of)`. When it doesn't there's nothing to show for the go, which
+    /// still takes one of the target's amount.
+    pub fn chance(&self) -> (u32, u32) {
+        self.chance
+    }
+
+    /// Parse a list of tasks written in RON, as in `assets/tasks.ron`. Chances have to be somewhere between never and
+    /// always, i.e. `0 < chance <= out_of`, and drops can't have fewer at most than at least.
+    pub fn parse_all(text: &str) -> Result<Vec<Task>, ron::error::SpannedError> {
+        ron::from_str(text)
+    }
+
+    /// The tasks the game ships with, as in `assets/tasks.ron`.
+    pub fn defaults() -> Vec<Task> {
+        assets::shipped(assets::TASKS)
+    }
+
+    /// Ticks the work takes for a villager carrying `inventory`, going by the quickest tool to hand.
+    pub fn duration_with(&self, inventory: &[String]) -> u64 {
+        self.quicker_with
+            .iter()
+            .filter(|(tool, _)| inventory.contains(tool))
+            .map(|(_, duration)| *duration)
+            .chain([self.duration])
+            .min()
+            .unwrap_or(self.duration)
+    }
+
+    /// The tools that see use when a villager carrying `inventory` does the work: everything it requires, and
+    /// whichever of `quicker_with` it goes quickest with.
+    pub fn tools_used<'a>(&'a self, inventory: &[String]) -> Vec<&'a str> {
+        let quickest = self
+            .quicker_with
+            .iter()
+            .filter(|(tool, duration)| inventory.contains(tool) && *duration < self.duration)
+            .min_by_key(|(_, duration)| *duration)
+            .map(|(tool, _)| tool.as_str());
+        self.requires
+            .iter()
+            .map(String::as_str)
+            .chain(quickest)
+            .collect()
+    }
+
+    /// The least time the work could ever take.
+    pub fn quickest(&self) -> u64 {
+        self.quicker_with
+            .iter()
+            .map(|(_, duration)| *duration)
+            .chain([self.duration])
+            .min()
+            .unwrap_or(self.duration)
+    }
+
+    /// Whether doing the work can come by `id`, one way or another.
+    pub fn produces(&self, id: &str) -> bool {
+        self.drops.iter().any(|(drop, _, _)| drop == id)
+            || self.gives.iter().any(|give| give == id)
+            || self.byproducts.iter().any(|byproduct| byproduct.item == id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drops_the_wrong_way_round_are_rejected() {
+        let tasks = Task::parse_all(
+            r#"[(name: "chopping", target: "tree", duration: 6, drops: [("wood", 3, 1)])]"#,
+        );
+        assert!(tasks.is_err());
+    }
+
+    #[test]
+    fn chances_that_never_come_off_are_rejected() {
+        let tasks =
+            Task::parse_all(r#"[(name: "foraging", target: "bush", duration: 1, chance: (0, 4))]"#);
+        assert!(tasks.is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "least <= most")]
+    fn tasks_made_in_code_are_checked_too() {
+        Task::new("mining", "rock", 5).with_drop("stone", 2, 1);
+    }
+}
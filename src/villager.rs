@@ -1,26 +1,31 @@
+use crate::item::Catalogue;
+use crate::map::Map;
+use crate::wear::Wear;
+
+pub type VillagerId = u32;
+
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
-pub(crate) struct Health(Option<u8>);
+pub struct Health(Option<u8>);
 
 impl Health {
     pub(crate) fn new(amount: u8) -> Self {
         Self(Some(amount))
     }
 
-    #[allow(dead_code)]
     pub(crate) fn replenish(&mut self, amount: u8) {
         if let Some(health) = self.0 {
             self.0 = Some(health.saturating_add(amount).min(100))
         }
     }
 
-    #[allow(dead_code)]
+    // Takes `amount` off, which is the end of whoever it is once there's nothing left.
     pub(crate) fn sap(&mut self, amount: u8) {
         if let Some(health) = self.0 {
-            self.0 = health.checked_sub(amount)
+            self.0 = health.checked_sub(amount).filter(|left| *left > 0)
         }
     }
 
-    pub(crate) fn check(&self) -> Option<u8> {
+    pub fn check(&self) -> Option<u8> {
         self.0
     }
 }
@@ -31,15 +36,149 @@ impl Default for Health {
     }
 }
 
-#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
-pub(crate) struct Villager {
-    pub(crate) position: (i64, i64),
-    pub(crate) health: Health,
-    pub(crate) inventory: Vec<String>,
+/// How full a villager is, out of 100.
+pub const MAX_SATIETY: u8 = 100;
+
+// Below this a villager starts thinking about food.
+pub(crate) const HUNGRY: u8 = 50;
+
+/// The most weight a villager can carry at once, unless told otherwise.
+pub const CARRY_CAPACITY: u32 = 30;
+
+// Health lost each time hunger strikes on an empty stomach.
+const STARVATION_DAMAGE: u8 = 1;
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Villager {
+    pub id: VillagerId,
+    pub position: (i64, i64),
+    pub health: Health,
+    pub satiety: u8,
+    pub inventory: Vec<String>,
+    pub capacity: u32,
+    /// The parts of the world the villager has seen for itself.
+    pub map: Map,
+    /// How worn the tools in the inventory are.
+    pub wear: Wear,
 }
 
 impl Villager {
-    pub(crate) fn is_alive(&self) -> bool {
+    pub fn is_alive(&self) -> bool {
         self.health.check().is_some()
     }
+
+    /// Total weight of everything in the inventory.
+    pub fn load(&self, catalogue: &Catalogue) -> u32 {
+        self.inventory.iter().map(|id| catalogue.weight(id)).sum()
+    }
+
+    /// Whether one more `id` would fit without going over capacity.
+    pub fn can_carry(&self, catalogue: &Catalogue, id: &str) -> bool {
+        self.load(catalogue) + catalogue.weight(id) <= self.capacity
+    }
+
+    /// Whether there's anything the villager couldn't take on without putting something down first.
+    pub fn is_full(&self, catalogue: &Catalogue) -> bool {
+        self.load(catalogue) + catalogue.heaviest() > self.capacity
+    }
+
+    // Ticks it takes to cover what would be `ticks` of walking unladen. A full load halves the villager's pace.
+    pub(crate) fn laden(&self, catalogue: &Catalogue, ticks: u64) -> u64 {
+        let capacity = self.capacity.max(1) as u64;
+        (ticks * (capacity + self.load(catalogue) as u64)).div_ceil(capacity)
+    }
+
+    // The other way round: how much unladen walking `ticks` of walking with the current load gets through.
+    pub(crate) fn unladen(&self, catalogue: &Catalogue, ticks: u64) -> u64 {
+        let capacity = self.capacity.max(1) as u64;
+        ticks * capacity / (capacity + self.load(catalogue) as u64)
+    }
+
+    pub fn count(&self, id: &str) -> usize {
+        self.inventory.iter().filter(|held| *held == id).count()
+    }
+
+    // Whether the villager would part with one `id`, i.e. it has more than the one.
+    pub(crate) fn can_spare(&self, id: &str) -> bool {
+        self.count(id) > 1
+    }
+
+    // Whether the villager carries anything to eat.
+    pub(crate) fn has_food(&self, catalogue: &Catalogue) -> bool {
+        self.inventory
+            .iter()
+            .any(|id| catalogue.nourishment(id).is_some())
+    }
+
+    // Whether the villager is in want of `id`: something to eat when it's hungry and has nothing.
+    pub(crate) fn needs(&self, catalogue: &Catalogue, id: &str) -> bool {
+        self.is_alive()
+            && self.can_carry(catalogue, id)
+            && self.satiety < HUNGRY
+            && catalogue.nourishment(id).is_some()
+            && !self.has_food(catalogue)
+    }
+
+    /// Uses left in the `tool` the villager would work with, if it carries one that wears out.
+    pub fn uses_left(&self, catalogue: &Catalogue, tool: &str) -> Option<u32> {
+        if self.count(tool) == 0 {
+            return None;
+        }
+        let durability = catalogue.durability(tool)?;
+        Some(durability.saturating_sub(self.wear.of(tool)))
+    }
+
+    // Puts another use on `tool`, which breaks and is thrown away once it has had all it can take.
+    pub(crate) fn use_tool(&mut self, catalogue: &Catalogue, tool: &str) {
+        let Some(left) = self.uses_left(catalogue, tool) else {
+            return;
+        };
+        if left <= 1 {
+            if let Some(broken) = self.inventory.iter().position(|held| held == tool) {
+                self.inventory.remove(broken);
+            }
+            self.wear.discard(tool);
+        } else {
+            self.wear.add_use(tool);
+        }
+    }
+
+    // Forgets the wear on tools the villager has used up rather than put down or handed on, which take their wear
+    // with them.
+    pub(crate) fn shed_wear(&mut self) {
+        self.wear.trim(&self.inventory);
+    }
+
+    pub fn is_starving(&self) -> bool {
+        self.satiety == 0
+    }
+
+    // Satiety runs down first, and once it's gone hunger eats into health instead.
+    pub(crate) fn get_hungry(&mut self) {
+        if self.is_starving() {
+            self.health.sap(STARVATION_DAMAGE);
+        } else {
+            self.satiety -= 1;
+        }
+    }
+
+    pub(crate) fn eat(&mut self, nourishment: u8) {
+        self.satiety = self.satiety.saturating_add(nourishment).min(MAX_SATIETY);
+        self.health.replenish(nourishment / 2);
+    }
+}
+
+impl Default for Villager {
+    fn default() -> Self {
+        Self {
+            id: VillagerId::default(),
+            position: (0, 0),
+            health: Health::default(),
+            satiety: MAX_SATIETY,
+            inventory: vec![],
+            capacity: CARRY_CAPACITY,
+            map: Map::default(),
+            wear: Wear::default(),
+        }
+    }
 }
@@ -0,0 +1,118 @@
+/// How worn the tools in a pile of items (a villager's inventory, or the stockpile) are, as the uses had out of each
+/// one. Tools without an entry are as good as new. Entries are kept sorted, so the same tools in the same state always
+/// make for the same `Wear`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Wear(Vec<(String, u32)>);
+
+impl Wear {
+    /// Uses had out of the most worn `tool`, which is the one that gets used first.
+    pub fn of(&self, tool: &str) -> u32 {
+        self.0
+            .iter()
+            .filter(|(kind, _)| kind == tool)
+            .map(|(_, uses)| *uses)
+            .max()
+            .unwrap_or(0)
+    }
+
+    // Puts another use on the most worn `tool`, or starts on a fresh one.
+    pub(crate) fn add_use(&mut self, tool: &str) {
+        match self.0.iter().rposition(|(kind, _)| kind == tool) {
+            Some(i) => self.0[i].1 += 1,
+            None => self.put(tool, 1),
+        }
+    }
+
+    // Forgets the most worn `tool`, once it's broken.
+    pub(crate) fn discard(&mut self, tool: &str) {
+        if let Some(i) = self.0.iter().rposition(|(kind, _)| kind == tool) {
+            self.0.remove(i);
+        }
+    }
+
+    // The wear on one of the `held` `id`s there are, taken off as it goes somewhere else. The freshest goes, so the one
+    // in use stays put.
+    pub(crate) fn take(&mut self, id: &str, held: usize) -> u32 {
+        let worn = self.0.iter().filter(|(kind, _)| kind == id).count();
+        if held > worn {
+            return 0;
+        }
+        match self.0.iter().position(|(kind, _)| kind == id) {
+            Some(i) => self.0.remove(i).1,
+            None => 0,
+        }
+    }
+
+    // Adds the wear on an `id` come from somewhere else.
+    pub(crate) fn put(&mut self, id: &str, uses: u32) {
+        if uses == 0 {
+            return;
+        }
+        let i = self
+            .0
+            .partition_point(|(kind, had)| (kind.as_str(), *had) < (id, uses));
+        self.0.insert(i, (id.to_string(), uses));
+    }
+
+    // Forgets the wear on any tools no longer among `contents` (used up in crafting, say), freshest first.
+    pub(crate) fn trim(&mut self, contents: &[String]) {
+        let mut i = 0;
+        while i < self.0.len() {
+            let kind = &self.0[i].0;
+            let worn = self.0.iter().filter(|(other, _)| other == kind).count();
+            let held = contents.iter().filter(|id| *id == kind).count();
+            if worn > held {
+                self.0.remove(i);
+            } else {
+                i += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn axes(n: usize) -> Vec<String> {
+        vec!["axe".to_string(); n]
+    }
+
+    #[test]
+    fn the_most_worn_tool_gets_used() {
+        let mut wear = Wear::default();
+        wear.put("axe", 3);
+        wear.put("axe", 5);
+
+        wear.add_use("axe");
+        assert_eq!(wear.of("axe"), 6);
+        assert_eq!(wear.take("axe", 2), 3);
+        assert_eq!(wear.of("axe"), 6);
+    }
+
+    #[test]
+    fn a_fresh_tool_goes_before_a_worn_one() {
+        let mut wear = Wear::default();
+        wear.add_use("axe");
+
+        assert_eq!(wear.take("axe", 2), 0);
+        assert_eq!(wear.of("axe"), 1);
+        assert_eq!(wear.take("axe", 1), 1);
+        assert_eq!(wear.of("axe"), 0);
+    }
+
+    #[test]
+    fn broken_tools_and_used_up_ones_are_forgotten() {
+        let mut wear = Wear::default();
+        wear.put("axe", 2);
+        wear.put("axe", 7);
+        wear.put("pickaxe", 1);
+
+        wear.discard("axe");
+        assert_eq!(wear.of("axe"), 2);
+
+        wear.trim(&axes(1));
+        assert_eq!(wear.of("axe"), 2);
+        assert_eq!(wear.of("pickaxe"), 0);
+    }
+}
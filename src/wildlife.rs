@@ -0,0 +1,92 @@
+// Wolves roam the wilds and go for any villager that strays near enough for them to catch its scent. They keep clear of
+// the village itself, which makes it the place to run to.
+use crate::actions::VillageState;
+use crate::movement::distance;
+use crate::villager::{Health, VillagerId};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use std::sync::Arc;
+
+pub type WolfId = u32;
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Wolf {
+    pub id: WolfId,
+    pub position: (i64, i64),
+    pub health: Health,
+}
+
+impl Wolf {
+    pub(crate) fn new(id: WolfId, position: (i64, i64)) -> Self {
+        Self {
+            id,
+            position,
+            health: Health::new(WOLF_HEALTH),
+        }
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.health.check().is_some()
+    }
+}
+
+// How much a wolf can take before it goes down.
+const WOLF_HEALTH: u8 = 30;
+// How close a villager has to be for a wolf to pick up its scent and come after it.
+pub(crate) const SCENT: u64 = 15;
+// How close a wolf has to be before a villager out in the open takes it for a threat.
+pub(crate) const DANGER: u64 = 6;
+// Ticks between each step a wolf takes, a little slower than a villager so that one can get away.
+const PACE: u64 = 2;
+// Ticks between bites, and how much health each one takes.
+const BITE_INTERVAL: u64 = 4;
+const BITE: u8 = 10;
+
+// Moves every wolf on a step, towards whoever it has the scent of or else wherever it fancies, and lets any that have
+// caught up with someone bite.
+pub(crate) fn prowl(state: &mut VillageState, ticks: u64, rng: &mut impl Rng) {
+    for i in 0..state.wolves.len() {
+        let position = state.wolves[i].position;
+        let prey = std::iter::once(&state.villager)
+            .chain(state.others.iter())
+            .filter(|villager| villager.is_alive() && !state.stockpile.contains(villager.position))
+            .map(|villager| (villager.id, villager.position))
+            .filter(|&(_, at)| distance(position, at) <= SCENT)
+            .min_by_key(|&(_, at)| distance(position, at));
+
+        match prey {
+            Some((id, at)) if distance(position, at) <= 1 => {
+                if ticks % BITE_INTERVAL == 0 {
+                    bite(state, id);
+                }
+            }
+            _ if ticks % PACE != 0 => {}
+            prey => {
+                let steps: Vec<_> = (-1..=1)
+                    .flat_map(|dx| (-1..=1).map(move |dy| (position.0 + dx, position.1 + dy)))
+                    .filter(|&step| {
+                        step != position
+                            && state.world.is_walkable(step)
+                            && !state.stockpile.contains(step)
+                    })
+                    .collect();
+                let step = match prey {
+                    Some((_, at)) => steps.iter().min_by_key(|&&step| distance(step, at)),
+                    None => steps.choose(rng),
+                };
+                if let Some(&step) = step {
+                    Arc::make_mut(&mut state.wolves)[i].position = step;
+                }
+            }
+        }
+    }
+}
+
+fn bite(state: &mut VillageState, id: VillagerId) {
+    if let Some(villager) = std::iter::once(&mut state.villager)
+        .chain(Arc::make_mut(&mut state.others).iter_mut())
+        .find(|villager| villager.id == id)
+    {
+        villager.health.sap(BITE);
+    }
+}
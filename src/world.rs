@@ -0,0 +1,101 @@
+use rand::Rng;
+
+// Terrain comes in square patches this many tiles across.
+const TERRAIN_PATCH_SIZE: i64 = 8;
+// Tries at finding somewhere to stand before giving up on the world as having nowhere.
+const PLACEMENT_TRIES: usize = 1000;
+// The village starts out in a clearing of this many patches square in the corner at the origin.
+const CLEARING_PATCHES: i64 = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Terrain {
+    Grass,
+    Rough,
+    Water,
+}
+
+impl Terrain {
+    /// Ticks it takes to step onto a tile of this terrain, or `None` if it can't be walked on at all.
+    pub fn move_cost(&self) -> Option<u64> {
+        match self {
+            Terrain::Grass => Some(1),
+            Terrain::Rough => Some(3),
+            Terrain::Water => None,
+        }
+    }
+}
+
+/// The size of the map. Positions run from `(0, 0)` up to, but not including, `(width, height)`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct World {
+    pub width: i64,
+    pub height: i64,
+    // Terrain is worked out from the seed on demand rather than stored, which keeps `World` cheap to copy into every
+    // state the planner looks at.
+    seed: u64,
+}
+
+impl World {
+    pub fn new(width: i64, height: i64) -> Self {
+        Self {
+            width,
+            height,
+            seed: 0,
+        }
+    }
+
+    pub(crate) fn with_seed(self, seed: u64) -> Self {
+        Self { seed, ..self }
+    }
+
+    pub fn contains(&self, (x, y): (i64, i64)) -> bool {
+        (0..self.width).contains(&x) && (0..self.height).contains(&y)
+    }
+
+    pub fn terrain(&self, (x, y): (i64, i64)) -> Terrain {
+        let patch = (
+            x.div_euclid(TERRAIN_PATCH_SIZE),
+            y.div_euclid(TERRAIN_PATCH_SIZE),
+        );
+        if (0..CLEARING_PATCHES).contains(&patch.0) && (0..CLEARING_PATCHES).contains(&patch.1) {
+            return Terrain::Grass;
+        }
+
+        match scramble(self.seed, patch) % 100 {
+            0..=5 => Terrain::Water,
+            6..=24 => Terrain::Rough,
+            _ => Terrain::Grass,
+        }
+    }
+
+    /// Whether a villager can stand at `position`.
+    pub fn is_walkable(&self, position: (i64, i64)) -> bool {
+        self.contains(position) && self.terrain(position).move_cost().is_some()
+    }
+
+    // Somewhere at random a villager could stand, unless there's nowhere (or nowhere to be found quickly).
+    pub(crate) fn random_position(&self, rng: &mut impl Rng) -> Option<(i64, i64)> {
+        if self.width <= 0 || self.height <= 0 {
+            return None;
+        }
+        std::iter::repeat_with(|| (rng.gen_range(0..self.width), rng.gen_range(0..self.height)))
+            .take(PLACEMENT_TRIES)
+            .find(|&position| self.is_walkable(position))
+    }
+}
+
+impl Default for World {
+    fn default() -> Self {
+        Self::new(150, 150)
+    }
+}
+
+// Cheap, well mixed hash of a seed and a pair of coordinates (splitmix64's finaliser).
+fn scramble(seed: u64, (x, y): (i64, i64)) -> u64 {
+    let mut z = seed
+        ^ (x as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+        ^ (y as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}